@@ -7,6 +7,8 @@ import { InlineEditor, InlineEditorRef } from './components/InlineEditor'
 import { LIMITS } from './types'
 
 type Section = 'chapters' | 'braindump' | 'synopsis' | 'outline' | 'characters' | 'worldbuilding' | 'genre' | 'styleGuide' | 'settings' | 'editor'
+type TaskKind = 'chat' | 'copy_edit' | 'rewrite' | 'summarize'
+type Route = { task: TaskKind } | { tier: 'small' | 'medium' | 'large' }
 
 function useApi() {
   const getApiUrl = () => {
@@ -22,12 +24,18 @@ function useApi() {
     return 'http://127.0.0.1:8000';
   };
 
-  async function heuristics(text: string) {
+  // The router answers failures with { code, message, retryable }.
+  async function apiError(res: Response) {
+    const body = await res.json().catch(() => null)
+    return new Error(body?.message ?? `API returned ${res.status}: ${res.statusText}`)
+  }
+
+  async function heuristics(text: string, project: string) {
     const apiUrl = getApiUrl();
     console.log('Calling API:', `${apiUrl}/api/heuristics`);
     const res = await fetch(`${apiUrl}/api/heuristics`, {
       method: 'POST', headers: {'content-type': 'application/json'},
-      body: JSON.stringify({ text, rules: { ban_em_dashes: true, narrative_contractions: false, max_sentence_words: 28 } })
+      body: JSON.stringify({ text, project, rules: { ban_em_dashes: true, narrative_contractions: false, max_sentence_words: 28 } })
     })
     if (!res.ok) {
       console.error('API error:', res.status, res.statusText);
@@ -35,24 +43,40 @@ function useApi() {
     }
     return res.json()
   }
-  async function minorEdit(text: string, model: string, issues?: any[]) {
+  async function minorEdit(text: string, model: string, project: string, issues?: any[]) {
     const res = await fetch(`${getApiUrl()}/api/minor_edit`, {
       method: 'POST', headers: {'content-type': 'application/json'},
-      body: JSON.stringify({ text, model, style: { tense: 'past', pov: 'close-third', narrative_contractions: false, dialogue_contractions: true, ban_em_dashes: true }, citations: [], issues })
+      body: JSON.stringify({ text, model, style: { tense: 'past', pov: 'close-third', narrative_contractions: false, dialogue_contractions: true, ban_em_dashes: true }, citations: [], issues, project })
     })
     if (!res.ok) {
-      throw new Error(`API returned ${res.status}: ${res.statusText}`)
+      throw await apiError(res)
     }
     return res.json()
   }
-  async function chat(prompt: string, model: string, context?: string) {
+  // The router picks the model tier from the task and the size of the text.
+  async function chat(prompt: string, route: Route, context?: string) {
     const res = await fetch(`${getApiUrl()}/api/chat`, {
       method: 'POST', headers: {'content-type': 'application/json'},
-      body: JSON.stringify({ prompt, model, context })
+      body: JSON.stringify({ prompt, ...route, context })
+    })
+    if (!res.ok) {
+      throw await apiError(res)
+    }
+    return res.json()
+  }
+  // Adds character names and unusual worldbuilding words (places and the
+  // like) to the project's spelling dictionary.
+  async function seedDictionary(project: string, characters: string[], worldbuilding: string) {
+    const res = await fetch(`${getApiUrl()}/api/dictionary/${encodeURIComponent(project)}/seed`, {
+      method: 'POST', headers: {'content-type': 'application/json'},
+      body: JSON.stringify({ characters, worldbuilding })
     })
+    if (!res.ok) {
+      throw await apiError(res)
+    }
     return res.json()
   }
-  return { heuristics, minorEdit, chat }
+  return { heuristics, minorEdit, chat, seedDictionary }
 }
 
 
@@ -66,7 +90,7 @@ export default function App() {
   const [isDraggingLeft, setIsDraggingLeft] = useState(false)
   const [generatingOutline, setGeneratingOutline] = useState(false)
   const editorRef = useRef<InlineEditorRef>(null)
-  const { chat } = useApi()
+  const { chat, seedDictionary } = useApi()
 
   const store = useStoryStore()
 
@@ -76,6 +100,13 @@ export default function App() {
     }
   }, [store.isLoaded, store.loadData])
 
+  useEffect(() => {
+    if (store.isLoaded && store.projectId) {
+      seedDictionary(store.projectId, store.characters.map(c => c.name), store.worldbuilding)
+        .catch(error => console.error('Failed to seed the project dictionary:', error))
+    }
+  }, [store.isLoaded, store.projectId])
+
   useEffect(() => {
     if (store.activeChapterId) {
       const chapter = store.chapters.find(c => c.id === store.activeChapterId)
@@ -87,7 +118,7 @@ export default function App() {
   }, [store.activeChapterId])
 
 
-  function selectModelForPrompt(prompt: string): string {
+  function selectTaskForPrompt(prompt: string): TaskKind {
     const lower = prompt.toLowerCase()
     
     const hasKeyword = (keywords: string[]) => {
@@ -108,14 +139,14 @@ export default function App() {
     ]
     
     if (hasKeyword(largeModelKeywords)) {
-      return store.models.large
+      return 'rewrite'
     }
     
     if (hasKeyword(mediumModelKeywords)) {
-      return store.models.medium
+      return 'copy_edit'
     }
     
-    return store.models.small
+    return 'chat'
   }
 
   async function onSendChat() {
@@ -127,11 +158,7 @@ export default function App() {
     setChapterResponse('Analyzing your chapter...')
 
     try {
-      const selectedModel = selectModelForPrompt(userPrompt)
-      
-      const modelInfo = selectedModel === store.models.large ? 'large model' : 
-                       selectedModel === store.models.medium ? 'medium model' : 
-                       selectedModel === store.models.small ? 'small model' : ''
+      const task = selectTaskForPrompt(userPrompt)
       
       const contextParts = []
       
@@ -180,12 +207,14 @@ export default function App() {
       
       const fullContext = contextParts.join('\n')
       
-      const response = await chat(userPrompt, selectedModel, fullContext)
-      const fullResponse = `${response.response}\n\n[Model used: ${modelInfo}]`
+      const response = await chat(userPrompt, { task }, fullContext)
+      const fallback = response.fallback_from ? `, ${response.fallback_from} is not installed` : ''
+      const fullResponse = `${response.response}\n\n[Model used: ${response.tier} model (${response.model}${fallback})]`
       setChapterResponse(fullResponse)
     } catch (err) {
       console.error('Chat error:', err)
-      setChapterResponse('Sorry, I cannot respond right now. Make sure Ollama is running with a model installed.')
+      const reason = err instanceof Error ? `\n\n(${err.message})` : ''
+      setChapterResponse(`Sorry, I cannot respond right now. Make sure Ollama is running with a model installed.${reason}`)
     } finally {
       setLoadingChat(false)
     }
@@ -206,7 +235,7 @@ ${store.synopsis}
 
 Generate a comprehensive story outline:`
 
-      const response = await chat(prompt, store.models.medium)
+      const response = await chat(prompt, { tier: 'medium' })
       store.updateField('outline', response.response)
       setActiveSection('outline')
     } catch (err) {
@@ -230,7 +259,7 @@ ${content}
 
 Summary:`
 
-      const response = await chat(prompt, store.models.small)
+      const response = await chat(prompt, { task: 'summarize' })
       store.updateChapter(chapterId, { summary: response.response.trim() })
     } catch (err) {
       console.error('Auto-summary generation error:', err)
@@ -13,7 +13,7 @@ interface StoryState extends StoryBible {
   activeChapterId: string | null
   isLoaded: boolean
   loadData: () => Promise<void>
-  updateField: (field: keyof Omit<StoryBible, 'characters' | 'chapters'>, value: string) => void
+  updateField: (field: keyof Omit<StoryBible, 'projectId' | 'characters' | 'chapters'>, value: string) => void
   updateModel: (size: keyof ModelConfig, model: string) => void
   addCharacter: (character: Character) => void
   updateCharacter: (id: string, character: Partial<Character>) => void
@@ -37,6 +37,7 @@ const persistToIndexedDB = async (state: Partial<StoryState>) => {
 }
 
 export const useStoryStore = create<StoryState>((set, get) => ({
+  projectId: '',
   braindump: '',
   synopsis: '',
   outline: '',
@@ -62,6 +63,7 @@ export const useStoryStore = create<StoryState>((set, get) => ({
       
       if (savedData) {
         set({
+          projectId: savedData.projectId || crypto.randomUUID(),
           braindump: savedData.braindump || '',
           synopsis: savedData.synopsis || '',
           outline: savedData.outline || '',
@@ -73,9 +75,11 @@ export const useStoryStore = create<StoryState>((set, get) => ({
           models: savedData.models || get().models,
           isLoaded: true
         })
+        if (!savedData.projectId) persistToIndexedDB(get())
         console.log('✅ Story data loaded from IndexedDB')
       } else {
-        set({ isLoaded: true })
+        set({ projectId: crypto.randomUUID(), isLoaded: true })
+        persistToIndexedDB(get())
         console.log('📝 No saved data found, starting fresh')
       }
     } catch (error) {
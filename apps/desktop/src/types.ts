@@ -21,6 +21,8 @@ export interface Chapter {
 }
 
 export interface StoryBible {
+  // Names the project on the router, e.g. for its spelling dictionary.
+  projectId: string
   braindump: string
   synopsis: string
   outline: string
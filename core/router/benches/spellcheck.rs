@@ -0,0 +1,36 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use router::spellcheck;
+use std::collections::HashSet;
+
+const MISSPELLINGS: &[&str] = &["teh", "becuase", "thougth", "realy", "shadwos", "corridoor", "definately"];
+
+const PARAGRAPH: &str = "She walkd through the corridoor, her heart beatting as the shadwos moved. \
+    Maybe she had herd something, or perhaps it was only the wind. She realy did not know \
+    what to think, but she knew she had to keep moveing before the light was gone. ";
+
+fn chapter() -> String {
+    PARAGRAPH.repeat(200)
+}
+
+fn bench_suggest(c: &mut Criterion) {
+    spellcheck::init();
+    c.bench_function("suggest_corrections", |b| {
+        b.iter(|| {
+            for word in MISSPELLINGS {
+                black_box(spellcheck::suggest_corrections(black_box(word), 3));
+            }
+        })
+    });
+}
+
+fn bench_check_chapter(c: &mut Criterion) {
+    spellcheck::init();
+    let text = chapter();
+    let user_words = HashSet::new();
+    c.bench_function("check_spelling_chapter", |b| {
+        b.iter(|| black_box(spellcheck::check_spelling(black_box(&text), &user_words)))
+    });
+}
+
+criterion_group!(benches, bench_suggest, bench_check_chapter);
+criterion_main!(benches);
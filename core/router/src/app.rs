@@ -0,0 +1,33 @@
+use axum::{routing::{delete, get, post}, Router};
+use tower_http::cors::{Any, CorsLayer};
+use crate::{chat, chat_sessions, heuristics, jobs, micro_edit, minor_edit, models, rebase, rewrite, tiers, user_dict, voice, AppState};
+
+pub fn router(state: AppState) -> Router {
+    let cors = CorsLayer::new()
+        .allow_origin(Any)
+        .allow_methods(Any)
+        .allow_headers(Any);
+
+    Router::new()
+        .route("/api/heuristics", post(heuristics::analyze))
+        .route("/api/minor_edit", post(minor_edit::edit))
+        .route("/api/minor_edit/stream", post(minor_edit::edit_stream))
+        .route("/api/minor_edit/targeted", post(micro_edit::edit))
+        .route("/api/minor_edit/rebase", post(rebase::rebase_edits))
+        .route("/api/rewrite", post(rewrite::rewrite))
+        .route("/api/dialogue/voice", post(voice::review_dialogue))
+        .route("/api/chat", post(chat::reply))
+        .route("/api/chat/stream", post(chat::reply_stream))
+        .route("/api/chat/sessions/:project", get(chat_sessions::list))
+        .route("/api/chat/sessions/:project/:id", get(chat_sessions::get).delete(chat_sessions::delete))
+        .route("/api/jobs/:id/cancel", post(jobs::cancel))
+        .route("/api/tiers", get(tiers::list))
+        .route("/api/models", get(models::list))
+        .route("/api/models/warm", post(models::warm))
+        .route("/api/health", get(models::health))
+        .route("/api/dictionary/:project", get(user_dict::list_words).post(user_dict::add_words))
+        .route("/api/dictionary/:project/seed", post(user_dict::seed))
+        .route("/api/dictionary/:project/:word", delete(user_dict::remove_word))
+        .layer(cors)
+        .with_state(state)
+}
@@ -0,0 +1,120 @@
+use axum::{extract::State, response::IntoResponse, Json};
+use serde::{Deserialize, Serialize};
+use crate::error::{require, ApiJson, RouterError};
+use crate::provider::ChatMessage;
+use crate::scheduler::{RouteReq, Routed, ServedBy, TaskKind};
+use crate::tiers::{GenOptions, Tier};
+use crate::{storage, streaming, AppState};
+
+#[derive(Debug, Deserialize)]
+pub struct ChatReq {
+    #[serde(default)]
+    model: String,
+    tier: Option<Tier>,
+    options: Option<GenOptions>,
+    // What the request is for and how much text it carries, used to pick a
+    // tier when neither model nor tier is given.
+    task: Option<TaskKind>,
+    input_size: Option<usize>,
+    prompt: String,
+    context: Option<String>,
+    request_id: Option<String>,
+    // Earlier turns sent by the client; when absent the stored session is used.
+    messages: Option<Vec<ChatMessage>>,
+    project: Option<String>,
+    session_id: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ChatResp {
+    request_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    session_id: Option<String>,
+    response: String,
+    #[serde(flatten)]
+    served_by: ServedBy,
+}
+
+const CHAT_SYSTEM_PROMPT: &str = "You are a helpful writing assistant for fiction authors. Provide specific, actionable advice.";
+const MAX_HISTORY_MESSAGES: usize = 40;
+
+// The (project, session) pair a chat is saved under, if the client asked for
+// one. A project without a session id starts a new session.
+fn chat_session(req: &ChatReq) -> Option<(String, String)> {
+    let project = req.project.clone().filter(|p| storage::valid_id(p))?;
+    let session = req
+        .session_id
+        .clone()
+        .filter(|s| storage::valid_id(s))
+        .unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+    Some((project, session))
+}
+
+fn chat_messages(state: &AppState, req: &ChatReq, session: Option<&(String, String)>) -> Vec<ChatMessage> {
+    let system = match &req.context {
+        Some(ctx) => format!("{}\n\nCONTEXT (user's current text):\n---\n{}\n---", CHAT_SYSTEM_PROMPT, ctx),
+        None => CHAT_SYSTEM_PROMPT.to_string(),
+    };
+    let history = match (&req.messages, session) {
+        (Some(messages), _) => messages.clone(),
+        (None, Some((project, id))) => state.chat_sessions.load(project, id).map(|s| s.messages).unwrap_or_default(),
+        (None, None) => Vec::new(),
+    };
+    let history: Vec<ChatMessage> = history.into_iter().filter(|m| m.role != "system").collect();
+    let skip = history.len().saturating_sub(MAX_HISTORY_MESSAGES);
+
+    let mut messages = vec![ChatMessage::new("system", system)];
+    messages.extend(history.into_iter().skip(skip));
+    messages.push(ChatMessage::new("user", req.prompt.clone()));
+    messages
+}
+
+fn save_exchange(state: &AppState, session: Option<&(String, String)>, prompt: &str, response: &str) {
+    let Some((project, id)) = session else { return };
+    let exchange = [ChatMessage::new("user", prompt), ChatMessage::new("assistant", response)];
+    if let Err(e) = state.chat_sessions.append(project, id, &exchange) {
+        tracing::warn!("failed to save chat session {}/{}: {}", project, id, e);
+    }
+}
+
+async fn validate(state: &AppState, req: &ChatReq, messages: &[ChatMessage]) -> Result<Routed, RouterError> {
+    require(&req.prompt, "prompt")?;
+    let input_size = req.input_size.unwrap_or_else(|| messages.iter().map(|m| m.content.chars().count()).sum());
+    let route = RouteReq {
+        task: req.task.unwrap_or(TaskKind::Chat),
+        input_size,
+        tier: req.tier,
+        model: &req.model,
+        options: req.options.as_ref(),
+    };
+    state.scheduler.route(state.llm.as_ref(), &state.tiers, route).await
+}
+
+pub async fn reply(State(state): State<AppState>, ApiJson(req): ApiJson<ChatReq>) -> Result<Json<ChatResp>, RouterError> {
+    let session = chat_session(&req);
+    let messages = chat_messages(&state, &req, session.as_ref());
+    let routed = validate(&state, &req, &messages).await?;
+    let job = state.jobs.start(req.request_id.clone());
+    let outcome = job.run(state.llm.chat(routed.model(), &messages, &routed.options)).await;
+    let request_id = job.id.clone();
+    job.finish();
+
+    let response = outcome??;
+    save_exchange(&state, session.as_ref(), &req.prompt, &response);
+    Ok(Json(ChatResp { request_id, session_id: session.map(|(_, id)| id), response, served_by: routed.served_by }))
+}
+
+pub async fn reply_stream(State(state): State<AppState>, ApiJson(req): ApiJson<ChatReq>) -> Result<impl IntoResponse, RouterError> {
+    let session = chat_session(&req);
+    let messages = chat_messages(&state, &req, session.as_ref());
+    let Routed { served_by, options } = validate(&state, &req, &messages).await?;
+    let job = state.jobs.start(req.request_id.clone());
+    let request_id = job.id.clone();
+    let llm = state.llm.clone();
+    let model = served_by.model.clone();
+    let upstream = async move { llm.chat_stream(&model, &messages, &options).await };
+    Ok(streaming::sse_generation(job, upstream, move |response| {
+        save_exchange(&state, session.as_ref(), &req.prompt, &response);
+        vec![streaming::done_event(&ChatResp { request_id, session_id: session.map(|(_, id)| id), response, served_by })]
+    }))
+}
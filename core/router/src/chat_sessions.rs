@@ -0,0 +1,126 @@
+use axum::{extract::{Path, State}, http::StatusCode, Json};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+use crate::error::RouterError;
+use crate::provider::ChatMessage;
+use crate::{storage, AppState};
+
+// Chat conversations saved per project as
+// <data_dir>/chats/<project>/<session>.json, so a conversation can be picked
+// up again in a later run.
+pub struct ChatSessions {
+    dir: PathBuf,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChatSession {
+    pub id: String,
+    pub project: String,
+    pub title: String,
+    pub updated_at: u64,
+    pub messages: Vec<ChatMessage>,
+}
+
+#[derive(Serialize)]
+pub struct SessionSummary {
+    pub id: String,
+    pub title: String,
+    pub updated_at: u64,
+    pub message_count: usize,
+}
+
+const TITLE_CHARS: usize = 60;
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs())
+}
+
+impl ChatSessions {
+    pub fn new(dir: PathBuf) -> Self {
+        ChatSessions { dir }
+    }
+
+    fn project_dir(&self, project: &str) -> PathBuf {
+        self.dir.join(project)
+    }
+
+    pub fn load(&self, project: &str, id: &str) -> Option<ChatSession> {
+        let path = storage::project_file(&self.project_dir(project), id, "json");
+        let raw = std::fs::read_to_string(path).ok()?;
+        serde_json::from_str(&raw).ok()
+    }
+
+    // Appends one exchange, creating the session on first use.
+    pub fn append(&self, project: &str, id: &str, messages: &[ChatMessage]) -> std::io::Result<ChatSession> {
+        let mut session = self.load(project, id).unwrap_or_else(|| ChatSession {
+            id: id.to_string(),
+            project: project.to_string(),
+            title: String::new(),
+            updated_at: 0,
+            messages: Vec::new(),
+        });
+        session.messages.extend(messages.iter().cloned());
+        if session.title.is_empty() {
+            if let Some(first) = session.messages.iter().find(|m| m.role == "user") {
+                session.title = first.content.chars().take(TITLE_CHARS).collect();
+            }
+        }
+        session.updated_at = now();
+
+        let dir = self.project_dir(project);
+        std::fs::create_dir_all(&dir)?;
+        let body = serde_json::to_string_pretty(&session)?;
+        std::fs::write(storage::project_file(&dir, id, "json"), body)?;
+        Ok(session)
+    }
+
+    pub fn list(&self, project: &str) -> Vec<SessionSummary> {
+        let Ok(entries) = std::fs::read_dir(self.project_dir(project)) else { return Vec::new() };
+        let mut sessions: Vec<SessionSummary> = entries
+            .filter_map(|e| e.ok())
+            .filter_map(|e| e.path().file_stem().map(|s| s.to_string_lossy().into_owned()))
+            .filter_map(|id| self.load(project, &id))
+            .map(|s| SessionSummary { id: s.id, title: s.title, updated_at: s.updated_at, message_count: s.messages.len() })
+            .collect();
+        sessions.sort_by_key(|s| std::cmp::Reverse(s.updated_at));
+        sessions
+    }
+
+    pub fn delete(&self, project: &str, id: &str) -> bool {
+        std::fs::remove_file(storage::project_file(&self.project_dir(project), id, "json")).is_ok()
+    }
+}
+
+fn check_ids(ids: &[&str]) -> Result<(), RouterError> {
+    match ids.iter().find(|id| !storage::valid_id(id)) {
+        Some(bad) => Err(RouterError::BadInput(format!("invalid id '{}'", bad))),
+        None => Ok(()),
+    }
+}
+
+pub async fn list(State(state): State<AppState>, Path(project): Path<String>) -> Result<Json<Vec<SessionSummary>>, RouterError> {
+    check_ids(&[&project])?;
+    Ok(Json(state.chat_sessions.list(&project)))
+}
+
+pub async fn get(
+    State(state): State<AppState>,
+    Path((project, id)): Path<(String, String)>,
+) -> Result<Json<ChatSession>, RouterError> {
+    check_ids(&[&project, &id])?;
+    state
+        .chat_sessions
+        .load(&project, &id)
+        .map(Json)
+        .ok_or_else(|| RouterError::NotFound(format!("no chat session '{}'", id)))
+}
+
+pub async fn delete(State(state): State<AppState>, Path((project, id)): Path<(String, String)>) -> Result<StatusCode, RouterError> {
+    check_ids(&[&project, &id])?;
+    if state.chat_sessions.delete(&project, &id) {
+        Ok(StatusCode::NO_CONTENT)
+    } else {
+        Err(RouterError::NotFound(format!("no chat session '{}'", id)))
+    }
+}
@@ -0,0 +1,116 @@
+use std::ops::Range;
+
+// Splits long texts into runs of whole lines that fit a model's context
+// window, preferring scene breaks, then blank lines, as cut points. Each
+// chunk also carries a little of the text before it so the model keeps
+// continuity across the cut without editing it twice.
+
+// Rough size of a token in characters of English prose.
+pub const CHARS_PER_TOKEN: usize = 4;
+
+// Estimated token count: characters over CHARS_PER_TOKEN, but never fewer
+// than one per word.
+pub fn estimate_tokens(text: &str) -> usize {
+    let chars = text.chars().count();
+    chars.div_ceil(CHARS_PER_TOKEN).max(text.split_whitespace().count())
+}
+
+#[derive(Debug, Clone)]
+pub struct Chunk {
+    // Zero-based indexes of the lines of the original text this chunk owns.
+    pub lines: Range<usize>,
+    // Character offset of the chunk in the original text.
+    pub start: usize,
+    // Those lines exactly as written, less the last line break.
+    pub text: String,
+    // Lines just before the chunk, for context only.
+    pub context: String,
+}
+
+// "***", "* * *", "#" and the like.
+fn is_scene_break(line: &str) -> bool {
+    let line = line.trim();
+    !line.is_empty() && line.chars().all(|c| matches!(c, '*' | '#' | '~' | '-' | '=' | '•' | ' '))
+}
+
+// Cuts `text` into chunks of at most `max_tokens` each, plus up to
+// `overlap_tokens` of preceding context. A single line longer than
+// `max_tokens` becomes a chunk of its own.
+pub fn chunks(text: &str, max_tokens: usize, overlap_tokens: usize) -> Vec<Chunk> {
+    let raw: Vec<&str> = text.split_inclusive('\n').collect();
+    let lines: Vec<&str> = raw.iter().map(|l| l.trim_end_matches(['\n', '\r'])).collect();
+    let tokens: Vec<usize> = lines.iter().map(|l| estimate_tokens(l) + 1).collect();
+
+    let mut cuts = vec![0];
+    let mut start = 0;
+    let mut size = 0;
+    for i in 0..lines.len() {
+        if size + tokens[i] > max_tokens && i > start {
+            let cut = best_cut(&lines, start, i);
+            cuts.push(cut);
+            start = cut;
+            size = tokens[cut..i].iter().sum();
+        }
+        size += tokens[i];
+    }
+    cuts.push(lines.len());
+
+    let mut offset = 0;
+    cuts.windows(2)
+        .filter(|w| w[0] < w[1])
+        .map(|w| {
+            let text = raw[w[0]..w[1]].concat();
+            let start = offset;
+            offset += text.chars().count();
+            let text = text.strip_suffix('\n').map_or(text.as_str(), |t| t.strip_suffix('\r').unwrap_or(t));
+            Chunk {
+                lines: w[0]..w[1],
+                start,
+                text: text.to_string(),
+                context: context_before(&lines, &tokens, w[0], overlap_tokens),
+            }
+        })
+        .collect()
+}
+
+// Up to `budget` tokens of the lines before `start`: whole lines where they
+// fit, then the closing words of the line that does not.
+fn context_before(lines: &[&str], tokens: &[usize], start: usize, mut budget: usize) -> String {
+    let mut from = start;
+    while from > 0 && tokens[from - 1] <= budget {
+        from -= 1;
+        budget -= tokens[from];
+    }
+    let mut context: Vec<String> = lines[from..start].iter().map(|l| l.to_string()).collect();
+    if from > 0 {
+        let mut words: Vec<&str> = Vec::new();
+        for word in lines[from - 1].split_whitespace().rev() {
+            let cost = estimate_tokens(word) + 1;
+            if cost > budget {
+                break;
+            }
+            budget -= cost;
+            words.push(word);
+        }
+        if !words.is_empty() {
+            words.push("...");
+            words.reverse();
+            context.insert(0, words.join(" "));
+        }
+    }
+    if context.iter().all(|l| l.trim().is_empty()) {
+        return String::new();
+    }
+    context.join("\n")
+}
+
+// Where to end a chunk that runs from `start` and cannot take line `end`:
+// after the last scene break or blank line in its second half, or else
+// right before `end`.
+fn best_cut(lines: &[&str], start: usize, end: usize) -> usize {
+    let half = start + (end - start) / 2;
+    let after = |pred: fn(&str) -> bool| (half..end).rev().find(|&i| pred(lines[i])).map(|i| i + 1);
+    after(is_scene_break)
+        .or_else(|| after(|l| l.trim().is_empty()))
+        .unwrap_or(end)
+}
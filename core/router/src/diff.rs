@@ -0,0 +1,218 @@
+use serde::{Deserialize, Serialize};
+use similar::{capture_diff_slices, Algorithm, DiffOp};
+use std::ops::Range;
+
+// Tracked-change edits between a text and its revision. Paragraphs (lines)
+// are matched first with a patience diff, so a merged or split paragraph
+// only touches its neighbours, then changed paragraphs are diffed word by
+// word. Ranges are character offsets into the original text.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EditKind {
+    Insert,
+    Delete,
+    Replace,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextEdit {
+    pub kind: EditKind,
+    // 1-based line of the original text the edit starts on.
+    pub line: usize,
+    pub start: usize,
+    pub end: usize,
+    pub old: String,
+    pub new: String,
+}
+
+pub fn edits(old: &str, new: &str) -> Vec<TextEdit> {
+    let old_lines = Lines::new(old);
+    let new_lines = Lines::new(new);
+    let mut edits = Vec::new();
+    for op in group(capture_diff_slices(Algorithm::Patience, &old_lines.keys, &new_lines.keys)) {
+        let (old_range, new_range) = op;
+        let old_part = old_lines.slice(old_range.clone());
+        let new_part = new_lines.slice(new_range);
+        let base = old_lines.offsets[old_range.start];
+        for (range, removed, added) in word_hunks(old_part, new_part) {
+            edits.push(TextEdit {
+                kind: match (removed.is_empty(), added.is_empty()) {
+                    (true, _) => EditKind::Insert,
+                    (_, true) => EditKind::Delete,
+                    _ => EditKind::Replace,
+                },
+                line: old_range.start + 1 + old_part.chars().take(range.start).filter(|c| *c == '\n').count(),
+                start: base + range.start,
+                end: base + range.end,
+                old: removed,
+                new: added,
+            });
+        }
+    }
+    edits.into_iter().filter_map(|e| clamp(e, old)).collect()
+}
+
+// A text's lines, each with its line break. A last line without one gets
+// a virtual "\n" so that texts with and without a final break diff alike;
+// `clamp` takes it back out of the edits.
+struct Lines {
+    text: String,
+    // Byte range of each line in `text`, break included.
+    ranges: Vec<Range<usize>>,
+    // Character offset of each line, plus one past the end.
+    offsets: Vec<usize>,
+    keys: Vec<String>,
+}
+
+impl Lines {
+    fn new(text: &str) -> Self {
+        let mut text = text.to_string();
+        if !text.ends_with('\n') {
+            text.push('\n');
+        }
+        let mut ranges = Vec::new();
+        let mut offsets = vec![0];
+        let mut at = 0;
+        for line in text.split_inclusive('\n') {
+            ranges.push(at..at + line.len());
+            offsets.push(offsets.last().unwrap() + line.chars().count());
+            at += line.len();
+        }
+        let keys = ranges.iter().map(|r| text[r.clone()].trim_end_matches(['\n', '\r']).to_string()).collect();
+        Lines { text, ranges, offsets, keys }
+    }
+
+    fn slice(&self, lines: Range<usize>) -> &str {
+        let start = self.ranges.get(lines.start).map_or(self.text.len(), |r| r.start);
+        let end = if lines.end > lines.start { self.ranges[lines.end - 1].end } else { start };
+        &self.text[start..end]
+    }
+}
+
+// Runs of changed lines as (old lines, new lines).
+fn group(ops: Vec<DiffOp>) -> Vec<(Range<usize>, Range<usize>)> {
+    let mut groups: Vec<(Range<usize>, Range<usize>)> = Vec::new();
+    for op in ops {
+        if let DiffOp::Equal { .. } = op {
+            continue;
+        }
+        let (old, new) = (op.old_range(), op.new_range());
+        match groups.last_mut() {
+            Some((o, n)) if o.end == old.start && n.end == new.start => {
+                o.end = old.end;
+                n.end = new.end;
+            }
+            _ => groups.push((old, new)),
+        }
+    }
+    groups
+}
+
+// Words, runs of whitespace, and single punctuation marks, each with its
+// character offset.
+fn tokens(text: &str) -> Vec<(usize, &str)> {
+    let class = |c: char| {
+        if c.is_alphanumeric() || c == '\'' || c == '’' {
+            1
+        } else if c.is_whitespace() {
+            2
+        } else {
+            3
+        }
+    };
+    let mut tokens: Vec<(usize, &str)> = Vec::new();
+    let mut start = 0;
+    let mut first_char = 0;
+    let mut prev = 0;
+    for (n, (i, c)) in text.char_indices().enumerate() {
+        let kind = class(c);
+        if i > 0 && (kind != prev || kind == 3) {
+            tokens.push((first_char, &text[start..i]));
+            start = i;
+            first_char = n;
+        }
+        prev = kind;
+    }
+    if start < text.len() {
+        tokens.push((first_char, &text[start..]));
+    }
+    tokens
+}
+
+// Word-level changes between two runs of lines, as (character range in
+// `old`, removed text, inserted text). Changes separated only by spaces are
+// reported as one.
+fn word_hunks(old: &str, new: &str) -> Vec<(Range<usize>, String, String)> {
+    let old_tokens = tokens(old);
+    let new_tokens = tokens(new);
+    // "\r\n" and "\n" are the same break.
+    let key = |t: &str| t.replace('\r', "");
+    let old_keys: Vec<String> = old_tokens.iter().map(|(_, t)| key(t)).collect();
+    let new_keys: Vec<String> = new_tokens.iter().map(|(_, t)| key(t)).collect();
+
+    let mut spans: Vec<(Range<usize>, Range<usize>)> = Vec::new();
+    for op in capture_diff_slices(Algorithm::Myers, &old_keys, &new_keys) {
+        if let DiffOp::Equal { .. } = op {
+            continue;
+        }
+        let (o, n) = (op.old_range(), op.new_range());
+        match spans.last_mut() {
+            Some((po, pn)) if only_spaces(&old_keys[po.end..o.start]) => {
+                po.end = o.end;
+                pn.end = n.end;
+            }
+            _ => spans.push((o, n)),
+        }
+    }
+
+    let char_at = |tokens: &[(usize, &str)], i: usize, total: usize| tokens.get(i).map_or(total, |t| t.0);
+    let old_len = old.chars().count();
+    spans
+        .into_iter()
+        .map(|(o, n)| {
+            let range = char_at(&old_tokens, o.start, old_len)..char_at(&old_tokens, o.end, old_len);
+            let removed: String = old_tokens[o].iter().map(|t| t.1).collect();
+            let added: String = new_tokens[n].iter().map(|t| t.1).collect();
+            (range, removed, added)
+        })
+        .collect()
+}
+
+fn only_spaces(tokens: &[String]) -> bool {
+    tokens.len() <= 1 && tokens.iter().all(|t| t.chars().all(|c| c == ' ' || c == '\t'))
+}
+
+// Removes the virtual final line break from an edit that reaches it.
+fn clamp(mut edit: TextEdit, text: &str) -> Option<TextEdit> {
+    let len = text.chars().count();
+    if edit.end <= len {
+        return Some(edit);
+    }
+    if edit.start >= len {
+        // Lines added after a last line that had no break.
+        if edit.start > len {
+            edit.line -= 1;
+        }
+        edit.start = len;
+        edit.new = format!("\n{}", edit.new.strip_suffix('\n').unwrap_or(&edit.new));
+    } else {
+        edit.old.pop();
+        if let Some(new) = edit.new.strip_suffix('\n') {
+            edit.new = new.to_string();
+        }
+        // Deleting the last lines takes the break before them too.
+        if edit.new.is_empty() && edit.start > 0 && text.chars().nth(edit.start - 1) == Some('\n') {
+            edit.start -= 1;
+            edit.old.insert(0, '\n');
+        }
+    }
+    edit.end = len;
+    if edit.old == edit.new {
+        return None;
+    }
+    if edit.new.is_empty() {
+        edit.kind = EditKind::Delete;
+    }
+    Some(edit)
+}
@@ -0,0 +1,63 @@
+use once_cell::sync::Lazy;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_json::Value;
+use std::ops::Range;
+
+// Structured edits as the model returns them: the span it changed, the
+// replacement, the rules it cites and why. Replies are checked against
+// core/schemas/edit-result.schema.json, the same schema the backend is asked
+// to constrain its output to.
+
+pub static SCHEMA: Lazy<Value> =
+    Lazy::new(|| serde_json::from_str(include_str!("../../schemas/edit-result.schema.json")).expect("edit-result schema is valid JSON"));
+
+pub static VALIDATOR: Lazy<jsonschema::Validator> =
+    Lazy::new(|| jsonschema::validator_for(&SCHEMA).expect("edit-result schema compiles"));
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ProposedEdit {
+    // The line the model says `old` is on; a hint for finding it.
+    pub line: usize,
+    pub old: String,
+    pub new: String,
+    #[serde(default)]
+    pub citations: Vec<String>,
+    #[serde(default)]
+    pub rationale: String,
+}
+
+#[derive(Deserialize)]
+pub struct EditResult {
+    pub edits: Vec<ProposedEdit>,
+}
+
+// The JSON object in a model reply, checked against `validator`, or why the
+// reply is unusable. Code fences and chatter around the object are ignored.
+pub fn parse<T: DeserializeOwned>(reply: &str, validator: &jsonschema::Validator) -> Result<T, String> {
+    let start = reply.find('{').ok_or("no JSON object in reply")?;
+    let end = reply.rfind('}').filter(|end| *end > start).ok_or("unterminated JSON object in reply")?;
+    let value: Value = serde_json::from_str(&reply[start..=end]).map_err(|e| e.to_string())?;
+    if let Some(err) = validator.iter_errors(&value).next() {
+        return Err(format!("{} at {}", err, err.instance_path));
+    }
+    serde_json::from_value(value).map_err(|e| e.to_string())
+}
+
+// Character range of `old` in `text`, whose first line is `first_line`
+// (1-based). Of several occurrences the one nearest the line the model
+// named wins; ranges in `taken` are skipped so two edits never overlap.
+pub fn anchor(text: &str, first_line: usize, edit: &ProposedEdit, taken: &[Range<usize>]) -> Option<Range<usize>> {
+    if edit.old.is_empty() {
+        return None;
+    }
+    text.match_indices(edit.old.as_str())
+        .map(|(at, _)| {
+            let start = text[..at].chars().count();
+            let line = first_line + text[..at].matches('\n').count();
+            (start..start + edit.old.chars().count(), line.abs_diff(edit.line))
+        })
+        .filter(|(range, _)| !taken.iter().any(|t| t.start < range.end && range.start < t.end))
+        .min_by_key(|(_, distance)| *distance)
+        .map(|(range, _)| range)
+}
@@ -0,0 +1,107 @@
+use axum::extract::rejection::JsonRejection;
+use axum::extract::FromRequest;
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use serde::Serialize;
+use thiserror::Error;
+use crate::jobs::Cancelled;
+use crate::provider::LlmError;
+
+// Every failing endpoint answers with `{code, message, retryable}` and a
+// status that says who is at fault: 422 for bad input, 502/503/504 when the
+// model server fails, is down or is too slow, and 499 (as nginx has it) when
+// the client cancelled the generation.
+#[derive(Error, Debug)]
+pub enum RouterError {
+    #[error("{0}")]
+    BadInput(String),
+    #[error("{0}")]
+    NotFound(String),
+    #[error(transparent)]
+    Llm(#[from] LlmError),
+    #[error("{0}")]
+    Storage(String),
+    #[error("the request was cancelled")]
+    Cancelled,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ErrorBody {
+    pub code: &'static str,
+    pub message: String,
+    pub retryable: bool,
+}
+
+impl RouterError {
+    pub fn status(&self) -> StatusCode {
+        match self {
+            RouterError::BadInput(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            RouterError::NotFound(_) => StatusCode::NOT_FOUND,
+            RouterError::Llm(LlmError::Timeout) => StatusCode::GATEWAY_TIMEOUT,
+            RouterError::Llm(LlmError::Unavailable { .. }) => StatusCode::SERVICE_UNAVAILABLE,
+            RouterError::Llm(_) => StatusCode::BAD_GATEWAY,
+            RouterError::Storage(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            RouterError::Cancelled => StatusCode::from_u16(499).unwrap(),
+        }
+    }
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            RouterError::BadInput(_) => "bad_input",
+            RouterError::NotFound(_) => "not_found",
+            RouterError::Llm(LlmError::Timeout) => "upstream_timeout",
+            RouterError::Llm(LlmError::Unavailable { .. }) => "upstream_unavailable",
+            RouterError::Llm(_) => "upstream_error",
+            RouterError::Storage(_) => "storage_error",
+            RouterError::Cancelled => "cancelled",
+        }
+    }
+
+    // Whether the same request may succeed if sent again. Network trouble
+    // is transient; a model that is missing or answers garbage is not.
+    pub fn retryable(&self) -> bool {
+        matches!(
+            self,
+            RouterError::Llm(LlmError::Timeout | LlmError::Unavailable { .. } | LlmError::Http(_))
+        )
+    }
+
+    pub fn body(&self) -> ErrorBody {
+        ErrorBody { code: self.code(), message: self.to_string(), retryable: self.retryable() }
+    }
+}
+
+impl IntoResponse for RouterError {
+    fn into_response(self) -> Response {
+        if let RouterError::Llm(e) = &self {
+            tracing::warn!("model request failed: {}", e);
+        }
+        (self.status(), Json(self.body())).into_response()
+    }
+}
+
+impl From<Cancelled> for RouterError {
+    fn from(_: Cancelled) -> Self {
+        RouterError::Cancelled
+    }
+}
+
+impl From<JsonRejection> for RouterError {
+    fn from(rejection: JsonRejection) -> Self {
+        RouterError::BadInput(rejection.body_text())
+    }
+}
+
+// `Json` for request bodies, with malformed input reported as a RouterError.
+#[derive(FromRequest)]
+#[from_request(via(Json), rejection(RouterError))]
+pub struct ApiJson<T>(pub T);
+
+pub fn require(value: &str, field: &str) -> Result<(), RouterError> {
+    if value.trim().is_empty() {
+        Err(RouterError::BadInput(format!("'{}' must not be empty", field)))
+    } else {
+        Ok(())
+    }
+}
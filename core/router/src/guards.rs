@@ -1 +1,96 @@
-// Offline guard ideas: reject any URL not localhost here before future outbound calls.
+use similar::{capture_diff_slices, Algorithm, DiffOp};
+use std::collections::{HashMap, HashSet};
+use crate::{segment, spellcheck};
+
+// Post-generation checks that keep a copy edit minor. An edit is refused
+// when it drops dialogue, brings in a name that is neither in the text nor
+// in the cast, or belongs to a paragraph the edits rewrite wholesale.
+
+// Share of a paragraph's words its edits may change together.
+const MAX_CHANGE_RATIO: f32 = 0.5;
+// Short paragraphs count as this long, so fixing two words in a line of
+// dialogue is not a rewrite.
+const MIN_PARAGRAPH_WORDS: usize = 12;
+
+// An edit to check: a character range of the text and its replacement.
+pub struct Change<'a> {
+    pub start: usize,
+    pub end: usize,
+    pub new: &'a str,
+}
+
+// Why each change is refused, or None where it passes. `cast` holds
+// lowercased names (and other words) the edits may bring in.
+pub fn review(text: &str, cast: &HashSet<String>, changes: &[Change]) -> Vec<Option<String>> {
+    let bytes: Vec<usize> = text.char_indices().map(|(i, _)| i).chain([text.len()]).collect();
+    let known: HashSet<String> = spellcheck::tokenize(text).iter().map(|t| t.text.to_lowercase()).collect();
+
+    let mut verdicts = Vec::with_capacity(changes.len());
+    // Paragraph start -> (words in the paragraph, words changed, changes).
+    let mut paragraphs: HashMap<usize, (usize, usize, Vec<usize>)> = HashMap::new();
+    for (i, change) in changes.iter().enumerate() {
+        let (start, end) = (bytes[change.start], bytes[change.end]);
+        let line_start = text[..start].rfind('\n').map_or(0, |p| p + 1);
+        let line_end = text[end..].find('\n').map_or(text.len(), |p| end + p);
+        let before = &text[line_start..line_end];
+        let after = format!("{}{}{}", &text[line_start..start], change.new, &text[end..line_end]);
+        let inserted = start - line_start..start - line_start + change.new.len();
+
+        let verdict = dropped_dialogue(before, &after).or_else(|| new_name(&after, inserted, &known, cast));
+        if verdict.is_none() {
+            let entry = paragraphs.entry(line_start).or_insert((0, 0, Vec::new()));
+            entry.0 = entry.0.max(before.split_whitespace().count());
+            entry.1 += words_changed(&text[start..end], change.new);
+            entry.2.push(i);
+        }
+        verdicts.push(verdict);
+    }
+
+    for (words, changed, members) in paragraphs.into_values() {
+        let ratio = changed as f32 / words.max(MIN_PARAGRAPH_WORDS) as f32;
+        if ratio > MAX_CHANGE_RATIO {
+            for i in members {
+                verdicts[i] = Some(format!(
+                    "rewrites {:.0}% of the paragraph; a minor edit changes at most {:.0}%",
+                    ratio.min(1.0) * 100.0,
+                    MAX_CHANGE_RATIO * 100.0
+                ));
+            }
+        }
+    }
+    verdicts
+}
+
+fn dropped_dialogue(before: &str, after: &str) -> Option<String> {
+    let (had, has) = (segment::dialogue(before).len(), segment::dialogue(after).len());
+    (has < had).then(|| "drops a line of dialogue".to_string())
+}
+
+// A capitalized word inside `inserted` that the text and cast do not know.
+// Capitals opening a sentence are left alone: without a full dictionary
+// they cannot be told apart from ordinary words.
+fn new_name(after: &str, inserted: std::ops::Range<usize>, known: &HashSet<String>, cast: &HashSet<String>) -> Option<String> {
+    spellcheck::tokenize(after)
+        .into_iter()
+        .filter(|t| t.start < inserted.end && t.end > inserted.start && !t.sentence_initial)
+        .filter(|t| t.text.chars().next().is_some_and(char::is_uppercase) && t.text != "I")
+        .find(|t| {
+            let lower = t.text.to_lowercase();
+            !known.contains(&lower) && !cast.contains(&lower)
+        })
+        .map(|t| format!("introduces \"{}\", a name not in the text or cast list", t.text))
+}
+
+// Words removed or added, whichever is more.
+fn words_changed(old: &str, new: &str) -> usize {
+    let old: Vec<&str> = old.split_whitespace().collect();
+    let new: Vec<&str> = new.split_whitespace().collect();
+    let (mut removed, mut added) = (0, 0);
+    for op in capture_diff_slices(Algorithm::Myers, &old, &new) {
+        if !matches!(op, DiffOp::Equal { .. }) {
+            removed += op.old_range().len();
+            added += op.new_range().len();
+        }
+    }
+    removed.max(added)
+}
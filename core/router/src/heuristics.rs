@@ -1,13 +1,15 @@
-use axum::Json;
+use axum::{extract::State, Json};
 use regex::Regex;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
-use crate::spellcheck;
+use std::collections::{HashMap, HashSet};
+use crate::error::ApiJson;
+use crate::segment::{self, Span};
+use crate::{spellcheck, AppState};
 
 #[derive(Deserialize)]
 pub struct HeurReq {
     pub text: String,
-    pub rules: Option<serde_json::Value>,
+    pub project: Option<String>,
 }
 
 #[derive(Serialize)]
@@ -24,16 +26,21 @@ pub struct Issue {
 #[derive(Serialize)]
 pub struct HeurResp { pub issues: Vec<Issue> }
 
-pub async fn analyze(Json(req): Json<HeurReq>) -> Json<HeurResp> {
-    let mut issues = Vec::new();
-    let text = &req.text;
+pub async fn analyze(State(state): State<AppState>, ApiJson(req): ApiJson<HeurReq>) -> Json<HeurResp> {
+    let user_words = state.user_dicts.for_project(req.project.as_deref());
+    Json(HeurResp { issues: issues(&req.text, &user_words) })
+}
 
-    issues.extend(check_concatenated_words(text));
-    issues.extend(check_spelling(text));
+// Every check, in report order. Offsets are byte offsets into `text`.
+pub fn issues(text: &str, user_words: &HashSet<String>) -> Vec<Issue> {
+    let mut issues = Vec::new();
+    issues.extend(check_concatenated_words(text, user_words));
+    issues.extend(check_spelling(text, user_words));
     issues.extend(check_adverbs(text));
     issues.extend(check_fillers(text));
     issues.extend(check_weak_starters(text));
     issues.extend(check_em_dashes(text));
+    issues.extend(check_narration_contractions(text));
     issues.extend(check_weak_verbs(text));
     issues.extend(check_cliches(text));
     issues.extend(check_prepositional_phrases(text));
@@ -45,18 +52,21 @@ pub async fn analyze(Json(req): Json<HeurReq>) -> Json<HeurResp> {
     issues.extend(check_telling_words(text));
     issues.extend(check_overused_conjunctions(text));
     issues.extend(check_double_spaces(text));
-
-    Json(HeurResp { issues })
+    issues.extend(check_sentence_case(text));
+    issues.extend(check_dialogue_tag_case(text));
+    issues.extend(check_dialogue_question_case(text));
+    issues.extend(check_shouting(text));
+    issues
 }
 
-fn check_concatenated_words(text: &str) -> Vec<Issue> {
+fn check_concatenated_words(text: &str, user_words: &HashSet<String>) -> Vec<Issue> {
     let mut issues = Vec::new();
     let word_re = Regex::new(r"\b[a-zA-Z]{6,}\b").unwrap();
     
     for m in word_re.find_iter(text) {
         let word = m.as_str().to_lowercase();
         
-        if spellcheck::is_word_correct(&word) {
+        if spellcheck::is_word_known(&word, user_words) {
             continue;
         }
         
@@ -65,7 +75,7 @@ fn check_concatenated_words(text: &str) -> Vec<Issue> {
             let right = &word[split_pos..];
             
             if left.len() >= 2 && right.len() >= 2 &&
-               spellcheck::is_word_correct(left) && spellcheck::is_word_correct(right) {
+               spellcheck::is_word_known(left, user_words) && spellcheck::is_word_known(right, user_words) {
                 let suggestion = format!("{} {}", left, right);
                 issues.push(Issue {
                     kind: "concatenated_words".into(),
@@ -159,6 +169,27 @@ fn check_em_dashes(text: &str) -> Vec<Issue> {
     issues
 }
 
+fn check_narration_contractions(text: &str) -> Vec<Issue> {
+    let mut issues = Vec::new();
+    let contraction_re = Regex::new(r"(?i)\b[a-z]+(?:n['’]t|['’](?:re|ll|ve|d|m))\b|\b(?:it|that|there|here|what|who|he|she|let)['’]s\b").unwrap();
+    let dialogue = segment::dialogue(text);
+
+    for m in contraction_re.find_iter(text) {
+        if dialogue.iter().any(|span| span.contains(m.start())) {
+            continue;
+        }
+        issues.push(Issue {
+            kind: "contraction".into(),
+            start: m.start(),
+            end: m.end(),
+            message: format!("Contraction '{}' in narration; house style spells it out", m.as_str()),
+            severity: "warning".into(),
+            suggestions: None,
+        });
+    }
+    issues
+}
+
 fn check_weak_verbs(text: &str) -> Vec<Issue> {
     let mut issues = Vec::new();
     let weak_verb_re = Regex::new(r"\b(was|were|is|are|am|been|being|be|had|has|have|do|does|did|get|gets|got|getting)\b").unwrap();
@@ -212,7 +243,6 @@ fn check_cliches(text: &str) -> Vec<Issue> {
 
 fn check_prepositional_phrases(text: &str) -> Vec<Issue> {
     let mut issues = Vec::new();
-    let prepositions = ["of", "in", "on", "at", "to", "for", "with", "by", "from", "about"];
     let prep_chain_re = Regex::new(
         r"\b(of|in|on|at|to|for|with|by|from|about)\s+\w+\s+(of|in|on|at|to|for|with|by|from|about)\s+\w+\s+(of|in|on|at|to|for|with|by|from|about)"
     ).unwrap();
@@ -262,7 +292,7 @@ fn check_punctuation(text: &str) -> Vec<Issue> {
 
 fn check_repetitive_words(text: &str) -> Vec<Issue> {
     let mut issues = Vec::new();
-    let sentences: Vec<&str> = text.split(|c| c == '.' || c == '!' || c == '?').collect();
+    let sentences: Vec<&str> = text.split(['.', '!', '?']).collect();
     
     for (idx, sentence) in sentences.iter().enumerate() {
         if sentence.trim().is_empty() { continue; }
@@ -276,12 +306,12 @@ fn check_repetitive_words(text: &str) -> Vec<Issue> {
         let mut word_positions: HashMap<String, Vec<usize>> = HashMap::new();
         for (pos, word) in words.iter().enumerate() {
             let word_lower = word.to_lowercase();
-            word_positions.entry(word_lower).or_insert_with(Vec::new).push(pos);
+            word_positions.entry(word_lower).or_default().push(pos);
         }
         
         for (word, positions) in word_positions {
             if positions.len() >= 2 {
-                let sentence_start = text.split(|c| c == '.' || c == '!' || c == '?')
+                let sentence_start = text.split(['.', '!', '?'])
                     .take(idx)
                     .map(|s| s.len() + 1)
                     .sum::<usize>();
@@ -303,7 +333,7 @@ fn check_repetitive_words(text: &str) -> Vec<Issue> {
 
 fn check_sentence_pacing(text: &str) -> Vec<Issue> {
     let mut issues = Vec::new();
-    let sentences: Vec<&str> = text.split(|c| c == '.' || c == '!' || c == '?')
+    let sentences: Vec<&str> = text.split(['.', '!', '?'])
         .filter(|s| !s.trim().is_empty())
         .collect();
     
@@ -311,7 +341,7 @@ fn check_sentence_pacing(text: &str) -> Vec<Issue> {
         let word_count = sentence.split_whitespace().count();
         
         if word_count > 35 {
-            let sentence_start = text.split(|c| c == '.' || c == '!' || c == '?')
+            let sentence_start = text.split(['.', '!', '?'])
                 .take(idx)
                 .map(|s| s.len() + 1)
                 .sum::<usize>();
@@ -327,7 +357,7 @@ fn check_sentence_pacing(text: &str) -> Vec<Issue> {
         }
         
         if word_count < 3 && !sentence.contains("!") {
-            let sentence_start = text.split(|c| c == '.' || c == '!' || c == '?')
+            let sentence_start = text.split(['.', '!', '?'])
                 .take(idx)
                 .map(|s| s.len() + 1)
                 .sum::<usize>();
@@ -457,9 +487,9 @@ fn check_double_spaces(text: &str) -> Vec<Issue> {
     issues
 }
 
-fn check_spelling(text: &str) -> Vec<Issue> {
+fn check_spelling(text: &str, user_words: &HashSet<String>) -> Vec<Issue> {
     let mut issues = Vec::new();
-    let spelling_errors = spellcheck::check_spelling(text);
+    let spelling_errors = spellcheck::check_spelling(text, user_words);
     
     for (start, end, word, suggestions) in spelling_errors {
         let message = if !suggestions.is_empty() {
@@ -479,3 +509,231 @@ fn check_spelling(text: &str) -> Vec<Issue> {
     }
     issues
 }
+
+const TAG_PRONOUNS: &[&str] = &["he", "she", "they", "we", "you", "it"];
+const SPEECH_VERBS: &[&str] = &[
+    "said", "asked", "replied", "whispered", "shouted", "muttered", "murmured",
+    "answered", "called", "cried", "yelled", "snapped", "added", "continued",
+];
+
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+fn prev_non_space(text: &str, pos: usize) -> Option<char> {
+    text[..pos].chars().rev().find(|c| !c.is_whitespace())
+}
+
+fn in_dialogue_after_question(text: &str, dialogue: &[Span], pos: usize) -> bool {
+    dialogue.iter().any(|d| d.contains(pos))
+        && matches!(prev_non_space(text, pos), Some('?') | Some('!'))
+}
+
+fn check_sentence_case(text: &str) -> Vec<Issue> {
+    let mut issues = Vec::new();
+    let dialogue = segment::dialogue(text);
+
+    for sentence in segment::sentences(text) {
+        let Some((pos, c)) = segment::first_letter(text, sentence) else { continue };
+        if !c.is_lowercase() || in_dialogue_after_question(text, &dialogue, pos) {
+            continue;
+        }
+        let word = segment::word_at(text, pos);
+        issues.push(Issue {
+            kind: "capitalization".into(),
+            start: word.start,
+            end: word.end,
+            message: format!("Sentence starts with lowercase '{}'", &text[word.start..word.end]),
+            severity: "error".into(),
+            suggestions: Some(vec![capitalize(&text[word.start..word.end])]),
+        });
+    }
+    issues
+}
+
+fn check_dialogue_tag_case(text: &str) -> Vec<Issue> {
+    let mut issues = Vec::new();
+    let sentence_starts: Vec<usize> = segment::sentences(text)
+        .into_iter()
+        .filter_map(|s| segment::first_letter(text, s).map(|(pos, _)| pos))
+        .collect();
+    let dialogue = segment::dialogue(text);
+
+    for (idx, quote) in dialogue.iter().enumerate() {
+        let inner = segment::dialogue_inner(text, *quote);
+        let ends_with_comma = text[inner.start..inner.end].trim_end().ends_with(',');
+
+        // "Hello," She said.
+        if ends_with_comma {
+            let after = &text[quote.end..];
+            let offset = after.len() - after.trim_start_matches([' ', '\t']).len();
+            let pos = quote.end + offset;
+            let word = segment::word_at(text, pos);
+            let tag = &text[word.start..word.end];
+            let lower = tag.to_lowercase();
+            if tag.chars().next().is_some_and(char::is_uppercase)
+                && (TAG_PRONOUNS.contains(&lower.as_str()) || SPEECH_VERBS.contains(&lower.as_str()))
+            {
+                issues.push(Issue {
+                    kind: "capitalization".into(),
+                    start: word.start,
+                    end: word.end,
+                    message: format!("Dialogue tag after a comma should be lowercase: '{}'", tag),
+                    severity: "error".into(),
+                    suggestions: Some(vec![lower]),
+                });
+            }
+        }
+
+        let Some((pos, c)) = segment::first_letter(text, inner) else { continue };
+        if sentence_starts.contains(&pos) {
+            continue;
+        }
+        let continues = idx > 0 && {
+            let prev = dialogue[idx - 1];
+            let prev_inner = segment::dialogue_inner(text, prev);
+            let between = &text[prev.end..quote.start];
+            !between.contains('\n')
+                && text[prev_inner.start..prev_inner.end].trim_end().ends_with(',')
+                && between.trim_end().ends_with(',')
+        };
+        let word = segment::word_at(text, pos);
+        let word_text = &text[word.start..word.end];
+
+        if continues && c.is_uppercase() && word_text != "I"
+            && spellcheck::is_word_correct(&word_text.to_lowercase())
+        {
+            // "I think," she said, "We should go."
+            issues.push(Issue {
+                kind: "capitalization".into(),
+                start: word.start,
+                end: word.end,
+                message: format!("Continued dialogue should not be capitalized: '{}'", word_text),
+                severity: "warning".into(),
+                suggestions: Some(vec![word_text.to_lowercase()]),
+            });
+        } else if !continues && c.is_lowercase() {
+            // She said, "we should go."
+            issues.push(Issue {
+                kind: "capitalization".into(),
+                start: word.start,
+                end: word.end,
+                message: format!("Dialogue should open with a capital letter: '{}'", word_text),
+                severity: "error".into(),
+                suggestions: Some(vec![capitalize(word_text)]),
+            });
+        }
+    }
+    issues
+}
+
+fn check_dialogue_question_case(text: &str) -> Vec<Issue> {
+    let mut issues = Vec::new();
+    let after_question_re = Regex::new(r"[?!]+\s+([a-z][\w']*)").unwrap();
+
+    for quote in segment::dialogue(text) {
+        let inner = segment::dialogue_inner(text, quote);
+        for caps in after_question_re.captures_iter(&text[inner.start..inner.end]) {
+            let word = caps.get(1).unwrap();
+            issues.push(Issue {
+                kind: "capitalization".into(),
+                start: inner.start + word.start(),
+                end: inner.start + word.end(),
+                message: format!("Lowercase '{}' after a question or exclamation inside dialogue", word.as_str()),
+                severity: "error".into(),
+                suggestions: Some(vec![capitalize(word.as_str())]),
+            });
+        }
+    }
+    issues
+}
+
+fn check_shouting(text: &str) -> Vec<Issue> {
+    let mut issues = Vec::new();
+    let caps_run_re = Regex::new(r"\b[A-Z][A-Z']*\b(?:[ \t,]+[A-Z][A-Z']*\b)+").unwrap();
+
+    for sentence in segment::sentences(text) {
+        let slice = &text[sentence.start..sentence.end];
+        for m in caps_run_re.find_iter(slice) {
+            let long_words = m.as_str()
+                .split([' ', '\t', ','])
+                .filter(|w| w.chars().filter(|c| c.is_alphabetic()).count() >= 2)
+                .count();
+            if long_words < 2 {
+                continue;
+            }
+            issues.push(Issue {
+                kind: "shouting".into(),
+                start: sentence.start + m.start(),
+                end: sentence.start + m.end(),
+                message: "All-caps run reads as shouting - consider italics or a stronger verb".into(),
+                severity: "warning".into(),
+                suggestions: None,
+            });
+        }
+    }
+    issues
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The words flagged with `kind`, in report order.
+    fn flagged<'a>(text: &'a str, kind: &str) -> Vec<&'a str> {
+        issues(text, &HashSet::new()).into_iter().filter(|i| i.kind == kind).map(|i| &text[i.start..i.end]).collect()
+    }
+
+    #[test]
+    fn capitalization() {
+        let cases: Vec<(&str, Vec<&str>)> = vec![
+            ("the door opened. it was late.", vec!["the", "it"]),
+            ("It was No. 5 on the list.", vec![]),
+            ("I said no. then he left.", vec!["then"]),
+            ("Mr. Vane waited.", vec![]),
+            ("He waited... and waited.", vec![]),
+            ("He waited... then he left.", vec![]),
+            ("\"Where?\" she asked.", vec![]),
+            ("\"Hello,\" She said.", vec!["She"]),
+            ("\"I think,\" she said, \"We should go.\"", vec!["We"]),
+            ("\"I think,\" she said, \"I should go.\"", vec![]),
+            ("She said, \"we should go.\"", vec!["we"]),
+            ("\"Are you sure? you look pale.\"", vec!["you"]),
+        ];
+        for (text, expected) in cases {
+            assert_eq!(flagged(text, "capitalization"), expected, "{}", text);
+        }
+    }
+
+    #[test]
+    fn narration_contractions() {
+        let cases: Vec<(&str, Vec<&str>)> = vec![
+            ("He didn't know. She'd left.", vec!["didn't", "She'd"]),
+            ("It’s late and they’re gone.", vec!["It’s", "they’re"]),
+            ("\"I don't know,\" she said. \"We'll see.\"", vec![]),
+            ("Anna's coat was wet.", vec![]),
+            ("At five o'clock they played rock'n'roll.", vec![]),
+            ("The dogs' bowls were empty.", vec![]),
+        ];
+        for (text, expected) in cases {
+            assert_eq!(flagged(text, "contraction"), expected, "{}", text);
+        }
+    }
+
+    #[test]
+    fn shouting() {
+        let cases: Vec<(&str, Vec<&str>)> = vec![
+            ("He yelled GET OUT NOW at them.", vec!["GET OUT NOW"]),
+            ("She worked for the FBI and NASA.", vec![]),
+            ("I AM here.", vec![]),
+            ("The US Navy ship left.", vec![]),
+        ];
+        for (text, expected) in cases {
+            assert_eq!(flagged(text, "shouting"), expected, "{}", text);
+        }
+    }
+}
@@ -0,0 +1,96 @@
+use reqwest::{Client, RequestBuilder, Response, StatusCode};
+use std::time::Duration;
+use crate::provider::LlmError;
+
+// Pooled HTTP client shared by the network backends. Connection failures and
+// 503 responses (Ollama and llama-server answer 503 while a model is still
+// loading) are retried with exponential backoff.
+#[derive(Debug, Clone)]
+pub struct HttpClient {
+    client: Client,
+    retries: u32,
+    backoff: Duration,
+}
+
+const MAX_BACKOFF: Duration = Duration::from_secs(10);
+
+fn unreachable(e: &reqwest::Error) -> String {
+    match e.url() {
+        Some(url) => format!("could not connect to {}", url.origin().ascii_serialization()),
+        None => "could not connect".to_string(),
+    }
+}
+
+fn env_u64(name: &str, default: u64) -> u64 {
+    std::env::var(name).ok().and_then(|v| v.parse().ok()).unwrap_or(default)
+}
+
+impl HttpClient {
+    pub fn new(connect_timeout: Duration, timeout: Duration, retries: u32, backoff: Duration) -> Self {
+        let client = Client::builder()
+            .connect_timeout(connect_timeout)
+            .timeout(timeout)
+            .build()
+            .unwrap_or_else(|e| {
+                tracing::warn!("failed to configure http client, using defaults: {}", e);
+                Client::new()
+            });
+        HttpClient { client, retries, backoff }
+    }
+
+    // LLM_CONNECT_TIMEOUT_SECS (default 5), LLM_TIMEOUT_SECS (default 300,
+    // the longest a whole generation may take, streams included),
+    // LLM_RETRIES (default 3) and LLM_RETRY_BACKOFF_MS (default 500, doubled
+    // on each retry).
+    pub fn from_env() -> Self {
+        HttpClient::new(
+            Duration::from_secs(env_u64("LLM_CONNECT_TIMEOUT_SECS", 5)),
+            Duration::from_secs(env_u64("LLM_TIMEOUT_SECS", 300)),
+            env_u64("LLM_RETRIES", 3) as u32,
+            Duration::from_millis(env_u64("LLM_RETRY_BACKOFF_MS", 500)),
+        )
+    }
+
+    pub fn get(&self, url: &str) -> RequestBuilder {
+        self.client.get(url)
+    }
+
+    pub fn post(&self, url: &str) -> RequestBuilder {
+        self.client.post(url)
+    }
+
+    // Sends the request once, for calls where a quick answer matters more
+    // than waiting for the server to come up.
+    pub async fn send_once(&self, req: RequestBuilder) -> Result<Response, LlmError> {
+        match req.send().await {
+            Err(e) if e.is_connect() => Err(LlmError::Unavailable { attempts: 1, reason: unreachable(&e) }),
+            result => Ok(result?),
+        }
+    }
+
+    // Sends the request, retrying while the server is unreachable or busy.
+    // Any other response, error statuses included, is returned to the caller.
+    pub async fn send(&self, req: RequestBuilder) -> Result<Response, LlmError> {
+        let mut attempt = 0;
+        loop {
+            let Some(this_try) = req.try_clone() else { return Ok(req.send().await?) };
+            let reason = match this_try.send().await {
+                Ok(resp) if resp.status() == StatusCode::SERVICE_UNAVAILABLE => {
+                    let body = resp.text().await.unwrap_or_default();
+                    format!("503 {}", body.trim())
+                }
+                Ok(resp) => return Ok(resp),
+                Err(e) if e.is_connect() => unreachable(&e),
+                Err(e) => return Err(e.into()),
+            };
+
+            attempt += 1;
+            if attempt > self.retries {
+                return Err(LlmError::Unavailable { attempts: attempt, reason });
+            }
+            let delay = self.backoff.saturating_mul(1 << (attempt - 1).min(16)).min(MAX_BACKOFF);
+            tracing::warn!("model server not ready ({}), retry {}/{} in {:?}", reason, attempt, self.retries, delay);
+            tokio::time::sleep(delay).await;
+        }
+    }
+}
@@ -0,0 +1,317 @@
+use encoding_rs::{Encoding, UTF_8};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+// Reader for Hunspell .dic/.aff pairs. Only the parts needed to enumerate
+// valid word forms are supported: prefix and suffix rules with conditions,
+// cross products, NEEDAFFIX and FORBIDDENWORD. Compounding, REP tables and
+// morphological fields are ignored. Files are decoded from the encoding
+// their SET line names; a dictionary in an encoding we do not know is
+// skipped.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FlagMode {
+    Char,
+    Long,
+    Num,
+}
+
+#[derive(Debug, Clone)]
+enum CondChar {
+    Any,
+    Set { chars: Vec<char>, negated: bool },
+}
+
+impl CondChar {
+    fn matches(&self, c: char) -> bool {
+        match self {
+            CondChar::Any => true,
+            CondChar::Set { chars, negated } => chars.contains(&c) != *negated,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct AffixEntry {
+    strip: String,
+    add: String,
+    condition: Vec<CondChar>,
+}
+
+#[derive(Debug, Clone)]
+struct AffixRule {
+    prefix: bool,
+    cross_product: bool,
+    entries: Vec<AffixEntry>,
+}
+
+#[derive(Debug, Default)]
+struct Affixes {
+    flag_mode: Option<FlagMode>,
+    rules: HashMap<String, AffixRule>,
+    aliases: Vec<String>,
+    need_affix: Option<String>,
+    forbidden: Option<String>,
+}
+
+// The encoding named by the .aff's SET line, UTF-8 when there is none.
+// Hunspell's names are mostly labels encoding_rs knows; ISO8859-1 decodes
+// as its superset windows-1252.
+fn encoding(aff: &[u8]) -> Result<&'static Encoding, String> {
+    let Some(name) = String::from_utf8_lossy(aff).lines().find_map(|l| l.strip_prefix("SET ")).map(|n| n.trim().to_string()) else {
+        return Ok(UTF_8);
+    };
+    let label = match name.to_ascii_lowercase().as_str() {
+        "microsoft-cp1251" => "cp1251".to_string(),
+        "tis620-2533" => "tis-620".to_string(),
+        other => other.to_string(),
+    };
+    Encoding::for_label(label.as_bytes()).ok_or_else(|| format!("unsupported encoding {}", name))
+}
+
+fn parse_flags(raw: &str, mode: FlagMode) -> Vec<String> {
+    match mode {
+        FlagMode::Char => raw.chars().map(String::from).collect(),
+        FlagMode::Long => raw
+            .chars()
+            .collect::<Vec<_>>()
+            .chunks(2)
+            .map(|c| c.iter().collect())
+            .collect(),
+        FlagMode::Num => raw.split(',').map(|f| f.trim().to_string()).filter(|f| !f.is_empty()).collect(),
+    }
+}
+
+fn parse_condition(raw: &str) -> Vec<CondChar> {
+    if raw == "." {
+        return Vec::new();
+    }
+    let mut out = Vec::new();
+    let mut chars = raw.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '.' => out.push(CondChar::Any),
+            '[' => {
+                let mut raw: Vec<char> = chars.by_ref().take_while(|&s| s != ']').collect();
+                let negated = raw.first() == Some(&'^');
+                if negated {
+                    raw.remove(0);
+                }
+                // A '-' between two characters is a range, as in [a-z].
+                let mut set = Vec::new();
+                let mut i = 0;
+                while i < raw.len() {
+                    if raw.get(i + 1) == Some(&'-') && i + 2 < raw.len() {
+                        set.extend(raw[i]..=raw[i + 2]);
+                        i += 3;
+                    } else {
+                        set.push(raw[i]);
+                        i += 1;
+                    }
+                }
+                out.push(CondChar::Set { chars: set, negated });
+            }
+            _ => out.push(CondChar::Set { chars: vec![c], negated: false }),
+        }
+    }
+    out
+}
+
+fn parse_aff(text: &str) -> Affixes {
+    let mut affixes = Affixes::default();
+    let mut lines = text.lines();
+
+    while let Some(line) = lines.next() {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        match fields.as_slice() {
+            ["FLAG", mode, ..] => {
+                affixes.flag_mode = Some(match *mode {
+                    "long" => FlagMode::Long,
+                    "num" => FlagMode::Num,
+                    _ => FlagMode::Char,
+                });
+            }
+            ["AF", count] => {
+                let Ok(count) = count.parse::<usize>() else { continue };
+                for alias in lines.by_ref().take(count) {
+                    let flags = alias.split_whitespace().nth(1).unwrap_or_default();
+                    affixes.aliases.push(flags.to_string());
+                }
+            }
+            ["NEEDAFFIX", flag, ..] => affixes.need_affix = Some(flag.to_string()),
+            ["FORBIDDENWORD", flag, ..] => affixes.forbidden = Some(flag.to_string()),
+            [kind @ ("PFX" | "SFX"), flag, cross, count, ..] => {
+                let Ok(count) = count.parse::<usize>() else { continue };
+                let mut rule = AffixRule { prefix: *kind == "PFX", cross_product: *cross == "Y", entries: Vec::new() };
+                for entry_line in lines.by_ref().take(count) {
+                    let f: Vec<&str> = entry_line.split_whitespace().collect();
+                    if f.len() < 4 || f[0] != *kind {
+                        continue;
+                    }
+                    let strip = if f[2] == "0" { String::new() } else { f[2].to_string() };
+                    let add = f[3].split('/').next().unwrap_or_default();
+                    let add = if add == "0" { String::new() } else { add.to_string() };
+                    let condition = parse_condition(f.get(4).copied().unwrap_or("."));
+                    rule.entries.push(AffixEntry { strip, add, condition });
+                }
+                affixes.rules.insert(flag.to_string(), rule);
+            }
+            _ => {}
+        }
+    }
+    affixes
+}
+
+fn apply_suffix(word: &str, entry: &AffixEntry) -> Option<String> {
+    let chars: Vec<char> = word.chars().collect();
+    let n = entry.condition.len();
+    if chars.len() < n || !word.ends_with(&entry.strip) || word.len() == entry.strip.len() {
+        return None;
+    }
+    let tail = &chars[chars.len() - n..];
+    if !entry.condition.iter().zip(tail).all(|(cond, &c)| cond.matches(c)) {
+        return None;
+    }
+    Some(format!("{}{}", &word[..word.len() - entry.strip.len()], entry.add))
+}
+
+fn apply_prefix(word: &str, entry: &AffixEntry) -> Option<String> {
+    let chars: Vec<char> = word.chars().collect();
+    let n = entry.condition.len();
+    if chars.len() < n || !word.starts_with(&entry.strip) || word.len() == entry.strip.len() {
+        return None;
+    }
+    if !entry.condition.iter().zip(&chars[..n]).all(|(cond, &c)| cond.matches(c)) {
+        return None;
+    }
+    Some(format!("{}{}", entry.add, &word[entry.strip.len()..]))
+}
+
+fn expand(stem: &str, flags: &[String], affixes: &Affixes, out: &mut Vec<String>) {
+    if affixes.forbidden.as_ref().is_some_and(|f| flags.contains(f)) {
+        return;
+    }
+    if !affixes.need_affix.as_ref().is_some_and(|f| flags.contains(f)) {
+        out.push(stem.to_string());
+    }
+
+    let rules: Vec<&AffixRule> = flags.iter().filter_map(|f| affixes.rules.get(f)).collect();
+    let mut suffixed = Vec::new();
+    for rule in rules.iter().filter(|r| !r.prefix) {
+        for entry in &rule.entries {
+            if let Some(form) = apply_suffix(stem, entry) {
+                if rule.cross_product {
+                    suffixed.push(form.clone());
+                }
+                out.push(form);
+            }
+        }
+    }
+    for rule in rules.iter().filter(|r| r.prefix) {
+        for entry in &rule.entries {
+            if let Some(form) = apply_prefix(stem, entry) {
+                out.push(form);
+            }
+            if rule.cross_product {
+                for base in &suffixed {
+                    if let Some(form) = apply_prefix(base, entry) {
+                        out.push(form);
+                    }
+                }
+            }
+        }
+    }
+}
+
+// All word forms generated by one .dic/.aff pair.
+pub fn load(dic_path: &Path, aff_path: &Path) -> std::io::Result<Vec<String>> {
+    let aff_bytes = std::fs::read(aff_path)?;
+    let dic_bytes = std::fs::read(dic_path)?;
+    let encoding = encoding(&aff_bytes).map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+    let affixes = parse_aff(&encoding.decode(&aff_bytes).0);
+    let mode = affixes.flag_mode.unwrap_or(FlagMode::Char);
+
+    let mut words = Vec::new();
+    for line in encoding.decode(&dic_bytes).0.lines().skip(1) {
+        let entry = line.split(['\t', ' ']).next().unwrap_or_default();
+        if entry.is_empty() {
+            continue;
+        }
+        let (stem, flags) = match entry.find('/').filter(|&i| i > 0 && !entry[..i].ends_with('\\')) {
+            Some(i) => {
+                let raw = &entry[i + 1..];
+                let raw = match raw.parse::<usize>() {
+                    Ok(n) if !affixes.aliases.is_empty() => affixes.aliases.get(n.wrapping_sub(1)).map_or("", String::as_str),
+                    _ => raw,
+                };
+                (&entry[..i], parse_flags(raw, mode))
+            }
+            None => (entry, Vec::new()),
+        };
+        expand(&stem.replace("\\/", "/"), &flags, &affixes, &mut words);
+    }
+    Ok(words)
+}
+
+// Finds `<name>.dic` files with a matching `.aff` in `dir`. When `names` is
+// non-empty only those dictionaries (e.g. "en_GB") are returned.
+pub fn find_dictionaries(dir: &Path, names: &[String]) -> Vec<(PathBuf, PathBuf)> {
+    let Ok(entries) = std::fs::read_dir(dir) else { return Vec::new() };
+    let mut found: Vec<(PathBuf, PathBuf)> = entries
+        .filter_map(|e| e.ok().map(|e| e.path()))
+        .filter(|p| p.extension().is_some_and(|ext| ext == "dic"))
+        .filter(|p| {
+            names.is_empty()
+                || p.file_stem().is_some_and(|s| names.iter().any(|n| s.to_string_lossy() == *n))
+        })
+        .map(|dic| (dic.clone(), dic.with_extension("aff")))
+        .filter(|(_, aff)| aff.exists())
+        .collect();
+    found.sort();
+    found
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn forms(aff: &[u8], dic: &[u8]) -> std::io::Result<Vec<String>> {
+        let dir = tempfile::tempdir().unwrap();
+        let (aff_path, dic_path) = (dir.path().join("t.aff"), dir.path().join("t.dic"));
+        std::fs::write(&aff_path, aff).unwrap();
+        std::fs::write(&dic_path, dic).unwrap();
+        load(&dic_path, &aff_path)
+    }
+
+    #[test]
+    fn expands_affixes() {
+        let aff = b"SFX S Y 4
+SFX S y ies [^aeiou]y
+SFX S 0 s [aeiou]y
+SFX S 0 es [sxzh]
+SFX S 0 s [^sxzhy]
+PFX U Y 1
+PFX U 0 un .
+";
+        let mut words = forms(aff, b"3\ncry/S\nday/S\nlock/SU\n").unwrap();
+        words.sort();
+        assert_eq!(words, ["cries", "cry", "day", "days", "lock", "locks", "unlock", "unlocks"]);
+    }
+
+    #[test]
+    fn condition_ranges() {
+        let cases = [("[a-c]", 'b', true), ("[a-c]", 'd', false), ("[^a-c]", 'd', true), ("[^a-c]", 'a', false), ("[a-]", '-', true)];
+        for (raw, c, expected) in cases {
+            assert_eq!(parse_condition(raw)[0].matches(c), expected, "{} on {:?}", raw, c);
+        }
+    }
+
+    #[test]
+    fn decodes_the_set_encoding() {
+        let words = forms(b"SET ISO8859-2\n", b"1\n\xbf\xf3\xb3w\n").unwrap();
+        assert_eq!(words, ["żółw"]);
+        let words = forms(b"SET ISO8859-1\n", b"1\ncaf\xe9\n").unwrap();
+        assert_eq!(words, ["café"]);
+        assert!(forms(b"SET X-MADE-UP\n", b"1\nword\n").is_err());
+    }
+}
@@ -0,0 +1,98 @@
+use axum::{extract::{Path, State}, Json};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio_util::sync::CancellationToken;
+use crate::error::RouterError;
+use crate::AppState;
+
+// In-flight LLM generations by request id. Cancelling a job fires its token;
+// the handler then drops the generation future, which closes the upstream
+// connection and makes Ollama stop generating.
+#[derive(Default)]
+pub struct JobRegistry {
+    jobs: Mutex<HashMap<String, (u64, CancellationToken)>>,
+    serial: AtomicU64,
+}
+
+impl JobRegistry {
+    // Registers a job under the client's id, or a fresh one if none was sent.
+    pub fn start(self: &Arc<Self>, request_id: Option<String>) -> JobGuard {
+        let id = request_id
+            .filter(|id| !id.is_empty())
+            .unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+        let token = CancellationToken::new();
+        let serial = self.serial.fetch_add(1, Ordering::Relaxed);
+        if let Some((_, previous)) = self.jobs.lock().unwrap().insert(id.clone(), (serial, token.clone())) {
+            previous.cancel();
+        }
+        JobGuard { id, serial, token, registry: Arc::clone(self), finished: false }
+    }
+
+    pub fn cancel(&self, id: &str) -> bool {
+        match self.jobs.lock().unwrap().remove(id) {
+            Some((_, token)) => {
+                token.cancel();
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+// Removes the job when the request ends, however it ends. A guard dropped
+// before `finish` means the client went away mid-generation.
+pub struct JobGuard {
+    pub id: String,
+    serial: u64,
+    token: CancellationToken,
+    registry: Arc<JobRegistry>,
+    finished: bool,
+}
+
+#[derive(Debug)]
+pub struct Cancelled;
+
+impl JobGuard {
+    // Runs `fut` unless the job is cancelled first, in which case `fut` is dropped.
+    pub async fn run<F: Future>(&self, fut: F) -> Result<F::Output, Cancelled> {
+        tokio::select! {
+            out = fut => Ok(out),
+            _ = self.token.cancelled() => {
+                tracing::info!(request_id = %self.id, "generation cancelled");
+                Err(Cancelled)
+            }
+        }
+    }
+
+    pub fn finish(mut self) {
+        self.finished = true;
+    }
+}
+
+impl Drop for JobGuard {
+    fn drop(&mut self) {
+        let mut jobs = self.registry.jobs.lock().unwrap();
+        if jobs.get(&self.id).is_some_and(|(serial, _)| *serial == self.serial) {
+            jobs.remove(&self.id);
+        }
+        if !self.finished && !self.token.is_cancelled() {
+            tracing::info!(request_id = %self.id, "client disconnected, generation dropped");
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct CancelResp {
+    pub id: String,
+    pub cancelled: bool,
+}
+
+pub async fn cancel(State(state): State<AppState>, Path(id): Path<String>) -> Result<Json<CancelResp>, RouterError> {
+    if !state.jobs.cancel(&id) {
+        return Err(RouterError::NotFound(format!("no running job '{}'", id)));
+    }
+    Ok(Json(CancelResp { id, cancelled: true }))
+}
@@ -0,0 +1,36 @@
+pub mod ollama_client;
+pub mod openai_client;
+pub mod phonetic;
+pub mod provider;
+pub mod rebase;
+pub mod scheduler;
+pub mod guards;
+pub mod canon_check;
+pub mod app;
+pub mod chat;
+pub mod chat_sessions;
+pub mod chunking;
+pub mod diff;
+pub mod edit_result;
+pub mod error;
+pub mod heuristics;
+pub mod http_client;
+pub mod hunspell;
+pub mod jobs;
+pub mod micro_edit;
+pub mod minor_edit;
+pub mod mock_client;
+pub mod models;
+pub mod rewrite;
+pub mod segment;
+pub mod speakers;
+pub mod spellcheck;
+pub mod state;
+pub mod storage;
+pub mod streaming;
+pub mod tiers;
+pub mod user_dict;
+pub mod verify;
+pub mod voice;
+
+pub use state::AppState;
@@ -0,0 +1,225 @@
+use axum::{extract::State, Json};
+use futures_util::{stream, StreamExt, TryStreamExt};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::ops::Range;
+use crate::diff::{self, EditKind};
+use crate::error::{require, ApiJson, RouterError};
+use crate::guards::{self, Change};
+use crate::minor_edit::{self, EditItem};
+use crate::provider::{LlmError, LlmProvider};
+use crate::scheduler::{RouteReq, ServedBy, TaskKind};
+use crate::segment::{self, Span};
+use crate::tiers::{GenOptions, Tier};
+use crate::{verify, AppState};
+
+// Targeted copy edits: each issue /api/heuristics flagged goes to the model
+// on its own, with the sentence it sits in and its complaint, and comes back
+// as one candidate edit inside that sentence. Nothing else in the text
+// changes, so the author can take the candidates one at a time.
+
+const MAX_PARALLEL_PROMPTS: usize = 2;
+// Each issue is a model call; more than this in one request is a chapter's
+// worth and belongs in /api/minor_edit.
+const MAX_ISSUES: usize = 50;
+
+#[derive(Debug, Deserialize)]
+pub struct FlaggedIssue {
+    kind: String,
+    // Byte range, as /api/heuristics reports it.
+    start: usize,
+    end: usize,
+    message: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MicroEditReq {
+    #[serde(default)]
+    model: String,
+    tier: Option<Tier>,
+    options: Option<GenOptions>,
+    input_size: Option<usize>,
+    text: String,
+    issues: Vec<FlaggedIssue>,
+    request_id: Option<String>,
+    #[serde(default)]
+    characters: Vec<String>,
+    project: Option<String>,
+    #[serde(default)]
+    drop_regressions: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Candidate {
+    // Index of the issue in the request.
+    issue: usize,
+    #[serde(flatten)]
+    edit: EditItem,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reason: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MicroEditResp {
+    request_id: String,
+    base_hash: String,
+    candidates: Vec<Candidate>,
+    // Candidates the guards rejected, with why.
+    refused: Vec<Candidate>,
+    // Issues the model left as they were.
+    unchanged: Vec<usize>,
+    #[serde(flatten)]
+    served_by: ServedBy,
+}
+
+// Where an issue sits: the sentences it touches and the line around them,
+// as byte ranges.
+struct Target {
+    sentence: Range<usize>,
+    line: Range<usize>,
+}
+
+fn target(text: &str, sentences: &[Span], index: usize, issue: &FlaggedIssue) -> Result<Target, RouterError> {
+    if issue.start > issue.end || issue.end > text.len() || !text.is_char_boundary(issue.start) || !text.is_char_boundary(issue.end) {
+        return Err(RouterError::BadInput(format!("issue {} is out of range for the text", index)));
+    }
+    let touched: Vec<&Span> = sentences.iter().filter(|s| s.start <= issue.end && issue.start < s.end.max(s.start + 1)).collect();
+    let start = touched.first().map_or(issue.start, |s| s.start.min(issue.start));
+    let end = touched.last().map_or(issue.end, |s| s.end.max(issue.end));
+    let line_start = text[..start].rfind('\n').map_or(0, |p| p + 1);
+    let line_end = text[end..].find('\n').map_or(text.len(), |p| end + p);
+    Ok(Target { sentence: start..end, line: line_start..line_end })
+}
+
+fn micro_edit_prompt(text: &str, target: &Target, issue: &FlaggedIssue) -> String {
+    format!(
+        "SYSTEM: You are a copy editor fixing one flagged problem in a manuscript sentence.
+
+PROBLEM ({}): {}
+FLAGGED WORDS: \"{}\"
+
+PARAGRAPH (for context only):
+{}
+
+INSTRUCTIONS:
+Rewrite the sentence below so the problem is gone. Change as little as you can and keep the author's voice. Return ONLY the rewritten sentence—no comments, no quotes around it.
+
+SENTENCE:
+{}",
+        issue.kind,
+        issue.message,
+        &text[issue.start..issue.end],
+        &text[target.line.clone()],
+        &text[target.sentence.clone()]
+    )
+}
+
+// The model's reply to each prompt, in order.
+pub async fn rewrite_all(llm: &dyn LlmProvider, model: &str, options: &GenOptions, prompts: &[String]) -> Result<Vec<String>, LlmError> {
+    stream::iter(0..prompts.len())
+        .map(|i| llm.generate(model, &prompts[i], options))
+        .buffered(MAX_PARALLEL_PROMPTS)
+        .try_collect()
+        .await
+}
+
+// A model's rewrite of `old` without the margins and wrapping quotes
+// models like to add.
+pub fn clean_rewrite<'a>(old: &str, rewrite: &'a str) -> &'a str {
+    let rewrite = rewrite.trim();
+    if old.starts_with(['"', '“']) {
+        return rewrite;
+    }
+    rewrite.strip_prefix('"').and_then(|r| r.strip_suffix('"')).unwrap_or(rewrite)
+}
+
+// The part of the sentence the rewrite changes, as one edit in the whole
+// text; None when it changes nothing.
+fn narrow(text: &str, sentence: Range<usize>, rewrite: &str, issue: &FlaggedIssue) -> Option<EditItem> {
+    let old = &text[sentence.clone()];
+    let rewrite = clean_rewrite(old, rewrite);
+    let edits = diff::edits(old, rewrite);
+    let (first, last) = (edits.first()?, edits.last()?);
+    let old_chars: Vec<char> = old.chars().collect();
+    let new_chars: Vec<char> = rewrite.chars().collect();
+    let new_end = new_chars.len().checked_sub(old_chars.len() - last.end)?;
+    let removed: String = old_chars[first.start..last.end].iter().collect();
+    let added: String = new_chars.get(first.start..new_end)?.iter().collect();
+    let base = text[..sentence.start].chars().count();
+    Some(EditItem {
+        kind: match (removed.is_empty(), added.is_empty()) {
+            (true, _) => EditKind::Insert,
+            (_, true) => EditKind::Delete,
+            _ => EditKind::Replace,
+        },
+        line: 1 + text[..sentence.start].matches('\n').count() + old_chars[..first.start].iter().filter(|c| **c == '\n').count(),
+        start: base + first.start,
+        end: base + last.end,
+        old: removed,
+        new: added,
+        citations: vec![issue.kind.clone()],
+        rationale: issue.message.clone(),
+        introduces: vec![],
+    })
+}
+
+// Checks each candidate on its own, since candidates for issues in the same
+// sentence overlap.
+fn review(req: &MicroEditReq, cast: &HashSet<String>, candidates: Vec<Candidate>) -> (Vec<Candidate>, Vec<Candidate>) {
+    let changes: Vec<Change> = candidates.iter().map(|c| Change { start: c.edit.start, end: c.edit.end, new: &c.edit.new }).collect();
+    let verdicts: Vec<Option<String>> = changes.iter().map(|c| guards::review(&req.text, cast, std::slice::from_ref(c)).remove(0)).collect();
+    let introduced = verify::each(&req.text, cast, &changes);
+
+    let mut kept = Vec::new();
+    let mut refused = Vec::new();
+    for ((mut candidate, verdict), found) in candidates.into_iter().zip(verdicts).zip(introduced) {
+        let reason = verdict.or_else(|| (req.drop_regressions && !found.is_empty()).then(|| format!("introduces errors: {}", found.join("; "))));
+        candidate.edit.introduces = found;
+        match reason {
+            Some(reason) => refused.push(Candidate { reason: Some(reason), ..candidate }),
+            None => kept.push(candidate),
+        }
+    }
+    (kept, refused)
+}
+
+pub async fn edit(State(state): State<AppState>, ApiJson(req): ApiJson<MicroEditReq>) -> Result<Json<MicroEditResp>, RouterError> {
+    require(&req.text, "text")?;
+    if req.issues.len() > MAX_ISSUES {
+        return Err(RouterError::BadInput(format!("at most {} issues can be fixed per request, got {}", MAX_ISSUES, req.issues.len())));
+    }
+    let sentences = segment::sentences(&req.text);
+    let targets = req
+        .issues
+        .iter()
+        .enumerate()
+        .map(|(i, issue)| target(&req.text, &sentences, i, issue))
+        .collect::<Result<Vec<_>, _>>()?;
+    let route = RouteReq {
+        task: TaskKind::CopyEdit,
+        input_size: req.input_size.unwrap_or_else(|| targets.iter().map(|t| req.text[t.line.clone()].chars().count()).max().unwrap_or(0)),
+        tier: req.tier,
+        model: &req.model,
+        options: req.options.as_ref(),
+    };
+    let routed = state.scheduler.route(state.llm.as_ref(), &state.tiers, route).await?;
+    let prompts: Vec<String> = targets.iter().zip(&req.issues).map(|(t, issue)| micro_edit_prompt(&req.text, t, issue)).collect();
+
+    let job = state.jobs.start(req.request_id.clone());
+    let outcome = job.run(rewrite_all(state.llm.as_ref(), routed.model(), &routed.options, &prompts)).await;
+    let request_id = job.id.clone();
+    job.finish();
+    let rewrites = outcome??;
+
+    let mut candidates = Vec::new();
+    let mut unchanged = Vec::new();
+    for (i, rewrite) in rewrites.iter().enumerate() {
+        match narrow(&req.text, targets[i].sentence.clone(), rewrite, &req.issues[i]) {
+            Some(edit) => candidates.push(Candidate { issue: i, edit, reason: None }),
+            None => unchanged.push(i),
+        }
+    }
+    let cast = minor_edit::cast(&state, req.project.as_deref(), &req.characters);
+    let (candidates, refused) = review(&req, &cast, candidates);
+    Ok(Json(MicroEditResp { request_id, base_hash: state.bases.keep(&req.text), candidates, refused, unchanged, served_by: routed.served_by }))
+}
@@ -0,0 +1,414 @@
+use async_stream::try_stream;
+use axum::{extract::State, response::IntoResponse, Json};
+use futures_util::{stream, StreamExt, TryStreamExt};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::ops::Range;
+use std::sync::{Arc, Mutex};
+use crate::chunking::{self, Chunk};
+use crate::diff::{self, EditKind};
+use crate::edit_result::{self, EditResult, ProposedEdit};
+use crate::error::{require, ApiJson, RouterError};
+use crate::guards::{self, Change};
+use crate::provider::{LlmError, LlmProvider, TextStream};
+use crate::scheduler::{RouteReq, Routed, ServedBy, TaskKind};
+use crate::tiers::{GenOptions, Tier};
+use crate::verify::{self, KindDelta};
+use crate::{streaming, AppState};
+
+#[derive(Debug, Deserialize)]
+pub struct MinorEditReq {
+    // Either a model name or a tier; the tier's model is used when the
+    // model is left out.
+    #[serde(default)]
+    model: String,
+    tier: Option<Tier>,
+    options: Option<GenOptions>,
+    // Routing hints; a copy edit of `text` unless told otherwise.
+    task: Option<TaskKind>,
+    input_size: Option<usize>,
+    // Style guide rules the model may cite alongside the house rules.
+    citations: Option<Vec<String>>,
+    // The author's style settings, e.g. {"tense": "past", "ban_em_dashes": true}.
+    style: serde_json::Value,
+    text: String,
+    issues: Option<Vec<serde_json::Value>>,
+    request_id: Option<String>,
+    // Names edits may bring in without being refused, besides those in the
+    // text and the project dictionary.
+    #[serde(default)]
+    characters: Vec<String>,
+    project: Option<String>,
+    // Move edits that introduce new errors to `refused` instead of only
+    // flagging them.
+    #[serde(default)]
+    drop_regressions: bool,
+}
+
+// One tracked change. `start`..`end` is the character range of `old` in
+// the text sent; `line` is the line it starts on. Clients send them back
+// to /api/minor_edit/rebase.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct EditItem {
+    pub kind: EditKind,
+    pub line: usize,
+    pub start: usize,
+    pub end: usize,
+    pub old: String,
+    pub new: String,
+    #[serde(default)]
+    pub citations: Vec<String>,
+    #[serde(default)]
+    pub rationale: String,
+    // New errors the edit brings in, such as an em dash or a misspelling.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub introduces: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RefusedEdit {
+    #[serde(flatten)]
+    edit: EditItem,
+    reason: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MinorEditResp {
+    request_id: String,
+    // Hash of the text edited, for rebasing the edits onto later versions.
+    base_hash: String,
+    edits: Vec<EditItem>,
+    // Edits the guards rejected, with why.
+    refused: Vec<RefusedEdit>,
+    // Heuristics issue counts per kind before and after the kept edits.
+    issue_delta: BTreeMap<String, KindDelta>,
+    #[serde(flatten)]
+    served_by: ServedBy,
+}
+
+// House rules, cited by id in structured edits.
+const HOUSE_RULES: &[(&str, &str)] = &[
+    ("em-dashes", "No em dashes in narration (house style)"),
+    ("contractions", "No contractions in narration; dialogue may contract naturally"),
+    ("tense-pov", "Maintain consistent past tense and close-third person POV"),
+    ("weak-verbs", "Strengthen weak verbs (was/were/is/had/has/get/got) → active, specific verbs"),
+    ("adverbs", "Eliminate adverbs where stronger verbs suffice"),
+    ("filler", "Remove filler words (really, very, just, quite, actually)"),
+    ("spelling", "Fix spelling errors and word concatenations"),
+    ("cliches", "Replace clichés and overused phrases with fresh language"),
+    ("passive-voice", "Convert passive constructions to active voice"),
+    ("clarity", "Ensure clarity, precision, and readability"),
+];
+
+const DEFAULT_RATIONALE: &str = "Copy edit: improved clarity, precision, and readability per publishing standards";
+
+// What the model is asked to send back.
+#[derive(Clone, Copy)]
+enum Reply {
+    // The whole chunk, edited.
+    Revision,
+    // The changes only, as JSON matching the edit-result schema.
+    Edits,
+}
+
+fn minor_edit_prompt(req: &MinorEditReq, chunk: &Chunk, reply: Reply) -> String {
+    let issues_summary = if let Some(issues) = &req.issues {
+        let mut summary = String::from("\n\nDETECTED ISSUES FOR REVIEW:\n");
+        let issue_types: std::collections::HashMap<String, usize> = issues.iter()
+            .filter_map(|i| i.get("kind").and_then(|k| k.as_str()))
+            .fold(std::collections::HashMap::new(), |mut acc, kind| {
+                *acc.entry(kind.to_string()).or_insert(0) += 1;
+                acc
+            });
+        
+        for (kind, count) in issue_types.iter() {
+            summary.push_str(&format!("- {} instances of {}\n", count, kind));
+        }
+        summary
+    } else {
+        String::new()
+    };
+
+    let standards: String = HOUSE_RULES.iter().map(|(id, rule)| format!("- [{}] {}\n", id, rule)).collect();
+    let sources = match req.citations.as_deref() {
+        Some(citations) if !citations.is_empty() => {
+            let list: String = citations.iter().map(|c| format!("- [{}]\n", c)).collect();
+            format!("\nSTYLE GUIDE SOURCES (cite these too where they apply):\n{}", list)
+        }
+        _ => String::new(),
+    };
+
+    let settings = match req.style.as_object() {
+        Some(style) if !style.is_empty() => {
+            let list: String = style
+                .iter()
+                .map(|(key, value)| format!("- {}: {}\n", key.replace('_', " "), value.as_str().map_or_else(|| value.to_string(), str::to_string)))
+                .collect();
+            format!("\nAUTHOR'S STYLE SETTINGS (follow these):\n{}", list)
+        }
+        _ => String::new(),
+    };
+
+    let preceding = if chunk.context.is_empty() {
+        String::new()
+    } else {
+        format!("\n\nPRECEDING TEXT (original, for continuity only; do not edit or repeat it):\n{}\n", chunk.context)
+    };
+
+    let (instructions, text) = match reply {
+        Reply::Revision => (
+            "Perform a thorough copy edit as you would for publication. Make each line stronger, clearer, and more engaging while preserving the author's voice and intent. Return ONLY the fully edited text with all improvements applied—no comments, no explanations, just the polished prose ready for the next editorial stage.",
+            chunk.text.clone(),
+        ),
+        Reply::Edits => (
+            "Perform a thorough copy edit as you would for publication, preserving the author's voice and intent. Each manuscript line below starts with its line number and \"| \". Reply with a JSON object {\"edits\": [...]} listing every change as {\"line\": the line number, \"old\": the exact words replaced, copied from that line without the number, \"new\": the replacement, \"citations\": [ids of the rules or sources applied], \"rationale\": one sentence on why}. Keep each \"old\" as short as the change allows. Reply {\"edits\": []} if nothing needs changing.",
+            chunk.text.lines().enumerate().map(|(i, line)| format!("{}| {}\n", chunk.lines.start + i + 1, line)).collect(),
+        ),
+    };
+
+    format!(
+        "SYSTEM: You are a senior copy editor at a prestigious publishing house reviewing a manuscript chapter. Provide a professional editorial critique with line-by-line markup.{}
+
+EDITORIAL STANDARDS:
+{}{}{}
+INSTRUCTIONS:
+{}
+
+{}
+MANUSCRIPT TEXT:
+{}",
+        issues_summary,
+        standards,
+        sources,
+        settings,
+        instructions,
+        preceding,
+        text
+    )
+}
+
+// Ollama's default window, assumed when the tier does not set one.
+const DEFAULT_CTX: usize = 2048;
+// Chunks are never cut smaller than this, however small the window.
+const MIN_CHUNK_TOKENS: usize = 256;
+const OVERLAP_TOKENS: usize = 120;
+const MAX_PARALLEL_CHUNKS: usize = 2;
+
+// Splits the text so that each chunk's prompt and its edited copy fit the
+// context window together, and the edited copy fits num_predict.
+fn plan_chunks(req: &MinorEditReq, options: &GenOptions) -> Vec<Chunk> {
+    let empty = Chunk { lines: 0..0, start: 0, text: String::new(), context: String::new() };
+    let overhead = chunking::estimate_tokens(&minor_edit_prompt(req, &empty, Reply::Edits)) + OVERLAP_TOKENS;
+    let ctx = options.num_ctx.map_or(DEFAULT_CTX, |n| n as usize);
+    let mut max_tokens = ctx.saturating_sub(overhead) / 2;
+    if let Some(n) = options.num_predict.filter(|n| *n > 0) {
+        max_tokens = max_tokens.min(n as usize);
+    }
+    let max_tokens = max_tokens.max(MIN_CHUNK_TOKENS);
+    let chunks = chunking::chunks(&req.text, max_tokens, OVERLAP_TOKENS);
+    if chunks.len() > 1 {
+        tracing::info!("editing {} lines in {} chunks of up to {} tokens", req.text.lines().count(), chunks.len(), max_tokens);
+    }
+    chunks
+}
+
+// Edits every chunk, a few at a time, in text order.
+async fn edit_chunks(llm: &dyn LlmProvider, model: &str, options: &GenOptions, req: &MinorEditReq, chunks: &[Chunk]) -> Result<Vec<EditItem>, LlmError> {
+    let edits: Vec<Vec<EditItem>> = stream::iter(0..chunks.len())
+        .map(|i| edit_chunk(llm, model, options, req, &chunks[i]))
+        .buffered(MAX_PARALLEL_CHUNKS)
+        .try_collect()
+        .await?;
+    Ok(edits.into_iter().flatten().collect())
+}
+
+// Asks for structured edits, and falls back to diffing a plain revision
+// when the reply does not parse or match the schema.
+async fn edit_chunk(llm: &dyn LlmProvider, model: &str, options: &GenOptions, req: &MinorEditReq, chunk: &Chunk) -> Result<Vec<EditItem>, LlmError> {
+    let reply = llm.generate_json(model, &minor_edit_prompt(req, chunk, Reply::Edits), &edit_result::SCHEMA, options).await?;
+    match edit_result::parse::<EditResult>(&reply, &edit_result::VALIDATOR) {
+        Ok(result) => Ok(anchored_edits(chunk, result.edits)),
+        Err(e) => {
+            tracing::warn!("unusable structured edits ({}), diffing a plain revision instead", e);
+            let revised = llm.generate(model, &minor_edit_prompt(req, chunk, Reply::Revision), options).await?;
+            Ok(diff_edits(chunk, &revised))
+        }
+    }
+}
+
+// Places the model's edits in the whole text. Edits whose `old` is not in
+// the chunk are dropped.
+fn anchored_edits(chunk: &Chunk, proposed: Vec<ProposedEdit>) -> Vec<EditItem> {
+    let mut taken: Vec<Range<usize>> = Vec::new();
+    let mut edits = Vec::new();
+    for edit in proposed.into_iter().filter(|e| e.old != e.new) {
+        let Some(range) = edit_result::anchor(&chunk.text, chunk.lines.start + 1, &edit, &taken) else {
+            tracing::debug!("dropping edit of {:?}: not in the text", edit.old);
+            continue;
+        };
+        let line = chunk.lines.start + 1 + chunk.text.chars().take(range.start).filter(|c| *c == '\n').count();
+        taken.push(range.clone());
+        edits.push(EditItem {
+            kind: if edit.new.is_empty() { EditKind::Delete } else { EditKind::Replace },
+            line,
+            start: chunk.start + range.start,
+            end: chunk.start + range.end,
+            old: edit.old,
+            new: edit.new,
+            citations: edit.citations,
+            rationale: if edit.rationale.trim().is_empty() { DEFAULT_RATIONALE.to_string() } else { edit.rationale },
+            introduces: vec![],
+        });
+    }
+    edits.sort_by_key(|e| e.start);
+    edits
+}
+
+// Edits from a chunk's revision, positioned in the whole text, so a chunk
+// that gains or loses lines cannot shift the edits of the others.
+fn diff_edits(chunk: &Chunk, revised: &str) -> Vec<EditItem> {
+    diff::edits(&chunk.text, &keep_margins(&chunk.text, revised))
+        .into_iter()
+        .map(|e| EditItem {
+            kind: e.kind,
+            line: chunk.lines.start + e.line,
+            start: chunk.start + e.start,
+            end: chunk.start + e.end,
+            old: e.old,
+            new: e.new,
+            citations: vec![],
+            rationale: DEFAULT_RATIONALE.to_string(),
+            introduces: vec![],
+        })
+        .collect()
+}
+
+// The revision with the original's leading and trailing whitespace, which
+// models tend to add or drop.
+pub fn keep_margins(text: &str, revised: &str) -> String {
+    let body = text.trim();
+    if body.is_empty() {
+        return text.to_string();
+    }
+    let lead = &text[..text.len() - text.trim_start().len()];
+    let tail = &text[text.trim_end().len()..];
+    format!("{}{}{}", lead, revised.trim(), tail)
+}
+
+// Lowercased names the edits may introduce: the cast plus the project
+// dictionary. Spellchecking the revision accepts them too.
+pub fn cast(state: &AppState, project: Option<&str>, characters: &[String]) -> HashSet<String> {
+    let mut cast = state.user_dicts.for_project(project);
+    cast.extend(characters.iter().flat_map(|name| name.split_whitespace()).map(str::to_lowercase));
+    cast
+}
+
+// Splits the edits into those that pass the guards and those refused.
+fn guard(text: &str, cast: &HashSet<String>, edits: Vec<EditItem>) -> (Vec<EditItem>, Vec<RefusedEdit>) {
+    let verdicts = guards::review(text, cast, &changes(&edits));
+    let mut kept = Vec::new();
+    let mut refused = Vec::new();
+    for (edit, verdict) in edits.into_iter().zip(verdicts) {
+        match verdict {
+            Some(reason) => refused.push(RefusedEdit { edit, reason }),
+            None => kept.push(edit),
+        }
+    }
+    if !refused.is_empty() {
+        tracing::info!("guards refused {} of {} edits", refused.len(), refused.len() + kept.len());
+    }
+    (kept, refused)
+}
+
+fn changes(edits: &[EditItem]) -> Vec<Change<'_>> {
+    edits.iter().map(|e| Change { start: e.start, end: e.end, new: &e.new }).collect()
+}
+
+// Runs the guards and the heuristics over the edits and builds the reply.
+fn respond(req: &MinorEditReq, cast: &HashSet<String>, edits: Vec<EditItem>, request_id: String, base_hash: String, served_by: ServedBy) -> MinorEditResp {
+    let (mut edits, mut refused) = guard(&req.text, cast, edits);
+    let mut report = verify::verify(&req.text, cast, &changes(&edits));
+    if req.drop_regressions && report.introduced.iter().any(|found| !found.is_empty()) {
+        let mut kept = Vec::new();
+        for (edit, found) in edits.into_iter().zip(report.introduced) {
+            if found.is_empty() {
+                kept.push(edit);
+            } else {
+                refused.push(RefusedEdit { reason: format!("introduces errors: {}", found.join("; ")), edit });
+            }
+        }
+        refused.sort_by_key(|r| r.edit.start);
+        edits = kept;
+        report = verify::verify(&req.text, cast, &changes(&edits));
+    }
+    for (edit, found) in edits.iter_mut().zip(report.introduced) {
+        edit.introduces = found;
+    }
+    MinorEditResp { request_id, base_hash, edits, refused, issue_delta: report.delta, served_by }
+}
+
+// The model and options to run the edit with.
+async fn validate(state: &AppState, req: &MinorEditReq) -> Result<Routed, RouterError> {
+    require(&req.text, "text")?;
+    let route = RouteReq {
+        task: req.task.unwrap_or(TaskKind::CopyEdit),
+        input_size: req.input_size.unwrap_or_else(|| req.text.chars().count()),
+        tier: req.tier,
+        model: &req.model,
+        options: req.options.as_ref(),
+    };
+    state.scheduler.route(state.llm.as_ref(), &state.tiers, route).await
+}
+
+pub async fn edit(State(state): State<AppState>, ApiJson(req): ApiJson<MinorEditReq>) -> Result<Json<MinorEditResp>, RouterError> {
+    let routed = validate(&state, &req).await?;
+    let chunks = plan_chunks(&req, &routed.options);
+    let job = state.jobs.start(req.request_id.clone());
+    let outcome = job.run(edit_chunks(state.llm.as_ref(), routed.model(), &routed.options, &req, &chunks)).await;
+    let request_id = job.id.clone();
+    job.finish();
+
+    let edits = outcome??;
+    let cast = cast(&state, req.project.as_deref(), &req.characters);
+    Ok(Json(respond(&req, &cast, edits, request_id, state.bases.keep(&req.text), routed.served_by)))
+}
+
+pub async fn edit_stream(State(state): State<AppState>, ApiJson(req): ApiJson<MinorEditReq>) -> Result<impl IntoResponse, RouterError> {
+    let Routed { served_by, options } = validate(&state, &req).await?;
+    let chunks = plan_chunks(&req, &options);
+    let prompts: Vec<String> = chunks.iter().map(|c| minor_edit_prompt(&req, c, Reply::Revision)).collect();
+    let cast = cast(&state, req.project.as_deref(), &req.characters);
+    let base_hash = state.bases.keep(&req.text);
+    let job = state.jobs.start(req.request_id.clone());
+    let request_id = job.id.clone();
+    let llm = state.llm.clone();
+    let model = served_by.model.clone();
+
+    // Chunks stream one after another, separated by a line break; each
+    // chunk's revision is kept apart for mapping the edits back.
+    let revisions = Arc::new(Mutex::new(Vec::new()));
+    let collected = revisions.clone();
+    let upstream = async move {
+        let tokens: TextStream = Box::pin(try_stream! {
+            for (i, prompt) in prompts.iter().enumerate() {
+                if i > 0 {
+                    yield "\n".to_string();
+                }
+                let mut tokens = llm.generate_stream(&model, prompt, &options).await?;
+                let mut revised = String::new();
+                while let Some(text) = tokens.next().await {
+                    let text = text?;
+                    revised.push_str(&text);
+                    yield text;
+                }
+                collected.lock().unwrap().push(revised);
+            }
+        });
+        Ok(tokens)
+    };
+    Ok(streaming::sse_generation(job, upstream, move |_| {
+        let revisions = revisions.lock().unwrap();
+        let edits = chunks.iter().zip(revisions.iter()).flat_map(|(chunk, revised)| diff_edits(chunk, revised)).collect();
+        vec![streaming::done_event(&respond(&req, &cast, edits, request_id, base_hash, served_by))]
+    }))
+}
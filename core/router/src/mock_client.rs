@@ -0,0 +1,209 @@
+use async_trait::async_trait;
+use futures_util::stream;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+use crate::provider::{ChatMessage, LlmError, LlmProvider, ModelInfo, TextStream};
+use crate::tiers::GenOptions;
+
+// In-process backend for tests and offline development. Replies are either
+// the last user message echoed back or a fixed script played in order, and
+// latency, upstream errors or malformed output can be injected.
+
+#[derive(Debug, Clone)]
+pub enum MockReply {
+    Echo,
+    Script(Vec<String>),
+}
+
+#[derive(Debug, Clone)]
+pub enum MockFault {
+    // The backend answers with an error, as Ollama does for a missing model.
+    Error(String),
+    // The backend sends output that does not parse. Streams yield the first
+    // half of the reply before failing.
+    Malformed,
+}
+
+// One call as the backend received it. Plain prompts are recorded as a
+// single user message.
+#[derive(Debug, Clone)]
+pub struct MockRequest {
+    pub model: String,
+    pub messages: Vec<ChatMessage>,
+    pub options: GenOptions,
+    // The schema asked for by `generate_json`.
+    pub format: Option<serde_json::Value>,
+}
+
+pub struct MockProvider {
+    reply: MockReply,
+    latency: Duration,
+    fault: Option<MockFault>,
+    next: AtomicUsize,
+    requests: Mutex<Vec<MockRequest>>,
+    models: Vec<String>,
+    warmed: Mutex<Vec<String>>,
+}
+
+impl MockProvider {
+    pub fn new(reply: MockReply) -> Self {
+        MockProvider {
+            reply,
+            latency: Duration::ZERO,
+            fault: None,
+            next: AtomicUsize::new(0),
+            requests: Mutex::new(Vec::new()),
+            models: Vec::new(),
+            warmed: Mutex::new(Vec::new()),
+        }
+    }
+
+    pub fn echo() -> Self {
+        MockProvider::new(MockReply::Echo)
+    }
+
+    pub fn scripted<S: Into<String>>(replies: impl IntoIterator<Item = S>) -> Self {
+        MockProvider::new(MockReply::Script(replies.into_iter().map(Into::into).collect()))
+    }
+
+    pub fn with_latency(mut self, latency: Duration) -> Self {
+        self.latency = latency;
+        self
+    }
+
+    pub fn with_fault(mut self, fault: MockFault) -> Self {
+        self.fault = Some(fault);
+        self
+    }
+
+    // Models reported as installed; those that have been warmed are
+    // reported as loaded.
+    pub fn with_models<S: Into<String>>(mut self, models: impl IntoIterator<Item = S>) -> Self {
+        self.models = models.into_iter().map(Into::into).collect();
+        self
+    }
+
+    // Configured from MOCK_LLM_SCRIPT (a JSON file holding an array of
+    // replies; echo when unset), MOCK_LLM_LATENCY_MS, MOCK_LLM_ERROR,
+    // MOCK_LLM_MALFORMED and MOCK_LLM_MODELS (comma separated).
+    pub fn from_env() -> Self {
+        let script = std::env::var("MOCK_LLM_SCRIPT").ok().and_then(|path| {
+            let text = std::fs::read_to_string(&path)
+                .map_err(|e| tracing::warn!("failed to read mock script {}: {}", path, e))
+                .ok()?;
+            serde_json::from_str::<Vec<String>>(&text)
+                .map_err(|e| tracing::warn!("mock script {} is not a JSON array of strings: {}", path, e))
+                .ok()
+        });
+        let mut provider = match script {
+            Some(replies) => MockProvider::scripted(replies),
+            None => MockProvider::echo(),
+        };
+        if let Ok(models) = std::env::var("MOCK_LLM_MODELS") {
+            provider = provider.with_models(models.split(',').map(str::trim).filter(|m| !m.is_empty()));
+        }
+        if let Some(ms) = std::env::var("MOCK_LLM_LATENCY_MS").ok().and_then(|v| v.parse().ok()) {
+            provider = provider.with_latency(Duration::from_millis(ms));
+        }
+        if let Ok(message) = std::env::var("MOCK_LLM_ERROR") {
+            provider = provider.with_fault(MockFault::Error(message));
+        } else if std::env::var("MOCK_LLM_MALFORMED").is_ok_and(|v| v == "1" || v == "true") {
+            provider = provider.with_fault(MockFault::Malformed);
+        }
+        provider
+    }
+
+    // Every call made so far, oldest first.
+    pub fn requests(&self) -> Vec<MockRequest> {
+        self.requests.lock().unwrap().clone()
+    }
+
+    fn fail(&self) -> Result<(), LlmError> {
+        match &self.fault {
+            Some(MockFault::Error(message)) => Err(LlmError::Upstream(format!("mock: {}", message))),
+            Some(MockFault::Malformed) => Err(malformed()),
+            None => Ok(()),
+        }
+    }
+
+    fn respond(&self, model: &str, messages: &[ChatMessage], options: &GenOptions, format: Option<&serde_json::Value>) -> String {
+        self.requests.lock().unwrap().push(MockRequest {
+            model: model.to_string(),
+            messages: messages.to_vec(),
+            options: options.clone(),
+            format: format.cloned(),
+        });
+        match &self.reply {
+            MockReply::Echo => messages
+                .iter()
+                .rev()
+                .find(|m| m.role == "user")
+                .map(|m| m.content.clone())
+                .unwrap_or_default(),
+            MockReply::Script(replies) if replies.is_empty() => String::new(),
+            MockReply::Script(replies) => {
+                let i = self.next.fetch_add(1, Ordering::Relaxed);
+                replies[i % replies.len()].clone()
+            }
+        }
+    }
+}
+
+fn malformed() -> LlmError {
+    LlmError::Json(serde_json::from_str::<serde_json::Value>("{\"message\": ").unwrap_err())
+}
+
+#[async_trait]
+impl LlmProvider for MockProvider {
+    fn name(&self) -> &'static str {
+        "mock"
+    }
+
+    async fn chat(&self, model: &str, messages: &[ChatMessage], options: &GenOptions) -> Result<String, LlmError> {
+        let reply = self.respond(model, messages, options, None);
+        tokio::time::sleep(self.latency).await;
+        self.fail().map(|_| reply)
+    }
+
+    async fn generate_json(&self, model: &str, prompt: &str, schema: &serde_json::Value, options: &GenOptions) -> Result<String, LlmError> {
+        let reply = self.respond(model, &[ChatMessage::new("user", prompt)], options, Some(schema));
+        tokio::time::sleep(self.latency).await;
+        self.fail().map(|_| reply)
+    }
+
+    async fn chat_stream(&self, model: &str, messages: &[ChatMessage], options: &GenOptions) -> Result<TextStream, LlmError> {
+        let reply = self.respond(model, messages, options, None);
+        tokio::time::sleep(self.latency).await;
+        let mut fragments: Vec<Result<String, LlmError>> = reply.split_inclusive(' ').map(|w| Ok(w.to_string())).collect();
+        match &self.fault {
+            Some(MockFault::Error(message)) => return Err(LlmError::Upstream(format!("mock: {}", message))),
+            Some(MockFault::Malformed) => {
+                fragments.truncate(fragments.len() / 2);
+                fragments.push(Err(malformed()));
+            }
+            None => {}
+        }
+        Ok(Box::pin(stream::iter(fragments)))
+    }
+
+    async fn models(&self) -> Result<Vec<ModelInfo>, LlmError> {
+        self.fail()?;
+        let warmed = self.warmed.lock().unwrap();
+        Ok(self
+            .models
+            .iter()
+            .map(|name| ModelInfo { name: name.clone(), loaded: Some(warmed.contains(name)), ..ModelInfo::default() })
+            .collect())
+    }
+
+    async fn warm(&self, model: &str) -> Result<(), LlmError> {
+        tokio::time::sleep(self.latency).await;
+        self.fail()?;
+        if !self.models.is_empty() && !self.models.iter().any(|m| m == model) {
+            return Err(LlmError::Upstream(format!("mock: model '{}' not found", model)));
+        }
+        self.warmed.lock().unwrap().push(model.to_string());
+        Ok(())
+    }
+}
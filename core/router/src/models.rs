@@ -0,0 +1,117 @@
+use axum::{extract::State, Json};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+use crate::error::{ApiJson, ErrorBody, RouterError};
+use crate::provider::{LlmError, ModelInfo};
+use crate::tiers::{same_model, Tier};
+use crate::AppState;
+
+// Model discovery, backend health and warm-up, all answered by whichever
+// backend the router is configured with.
+
+const HEALTH_TIMEOUT: Duration = Duration::from_secs(3);
+
+#[derive(Serialize)]
+pub struct ModelsResp {
+    pub backend: &'static str,
+    pub models: Vec<ModelInfo>,
+}
+
+pub async fn list(State(state): State<AppState>) -> Result<Json<ModelsResp>, RouterError> {
+    let models = state.llm.models().await?;
+    Ok(Json(ModelsResp { backend: state.llm.name(), models }))
+}
+
+#[derive(Serialize)]
+pub struct TierStatus {
+    pub model: String,
+    // None when the backend could not be reached.
+    pub installed: Option<bool>,
+    pub loaded: Option<bool>,
+}
+
+#[derive(Serialize)]
+pub struct BackendHealth {
+    pub name: &'static str,
+    pub reachable: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<ErrorBody>,
+    pub models_installed: usize,
+    pub models_loaded: Vec<String>,
+}
+
+#[derive(Serialize)]
+pub struct HealthResp {
+    // "ok", "degraded" when a tier's model is not installed, or
+    // "unavailable" when the backend cannot be reached.
+    pub status: &'static str,
+    pub version: &'static str,
+    pub backend: BackendHealth,
+    pub tiers: BTreeMap<Tier, TierStatus>,
+}
+
+pub async fn health(State(state): State<AppState>) -> Json<HealthResp> {
+    let listed = match tokio::time::timeout(HEALTH_TIMEOUT, state.llm.models()).await {
+        Ok(result) => result.map_err(RouterError::from),
+        Err(_) => Err(RouterError::Llm(LlmError::Timeout)),
+    };
+    let models = listed.as_ref().ok();
+
+    let tiers: BTreeMap<Tier, TierStatus> = Tier::ALL
+        .into_iter()
+        .map(|tier| {
+            let model = state.tiers.get(tier).model.clone();
+            let found = models.map(|ms| ms.iter().find(|m| same_model(&m.name, &model)));
+            let status = TierStatus {
+                installed: found.map(|m| m.is_some()),
+                loaded: found.flatten().and_then(|m| m.loaded),
+                model,
+            };
+            (tier, status)
+        })
+        .collect();
+
+    let status = match models {
+        None => "unavailable",
+        Some(_) if tiers.values().any(|t| t.installed == Some(false)) => "degraded",
+        Some(_) => "ok",
+    };
+    let backend = BackendHealth {
+        name: state.llm.name(),
+        reachable: models.is_some(),
+        error: listed.as_ref().err().map(RouterError::body),
+        models_installed: models.map_or(0, Vec::len),
+        models_loaded: models
+            .into_iter()
+            .flatten()
+            .filter(|m| m.loaded == Some(true))
+            .map(|m| m.name.clone())
+            .collect(),
+    };
+    Json(HealthResp { status, version: env!("CARGO_PKG_VERSION"), backend, tiers })
+}
+
+#[derive(Deserialize)]
+pub struct WarmReq {
+    pub tier: Option<Tier>,
+    #[serde(default)]
+    pub model: String,
+}
+
+#[derive(Serialize)]
+pub struct WarmResp {
+    pub model: String,
+    pub elapsed_ms: u128,
+}
+
+pub async fn warm(State(state): State<AppState>, ApiJson(req): ApiJson<WarmReq>) -> Result<Json<WarmResp>, RouterError> {
+    if req.tier.is_none() && req.model.trim().is_empty() {
+        return Err(RouterError::BadInput("give a 'tier' or a 'model' to warm".into()));
+    }
+    let (_, model, _) = state.tiers.resolve(req.tier, &req.model, None)?;
+    let started = Instant::now();
+    state.llm.warm(&model).await?;
+    tracing::info!("warmed {} in {:?}", model, started.elapsed());
+    Ok(Json(WarmResp { model, elapsed_ms: started.elapsed().as_millis() }))
+}
@@ -1,35 +1,192 @@
-use reqwest::Client;
-use serde::{Deserialize, Serialize};
-use thiserror::Error;
-
-#[derive(Error, Debug)]
-pub enum OllamaErr {
-    #[error("http: {0}")]
-    Http(#[from] reqwest::Error),
-    #[error("ollama returned empty response")] 
-    Empty,
-}
+use async_stream::try_stream;
+use async_trait::async_trait;
+use futures_util::{Stream, StreamExt};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use crate::http_client::HttpClient;
+use crate::provider::{body_lines, completion, ChatMessage, LlmError, LlmProvider, ModelInfo, TextStream};
+use crate::tiers::GenOptions;
 
 #[derive(Serialize)]
 struct GenerateReq<'a> {
     model: &'a str,
     prompt: &'a str,
     stream: bool,
-    options: serde_json::Value,
+    options: &'a GenOptions,
+    // "json" or a JSON schema the reply must match.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    format: Option<&'a serde_json::Value>,
 }
 
 #[derive(Deserialize)]
 struct GenerateResp {
     response: Option<String>,
     done: Option<bool>,
+    error: Option<String>,
+}
+
+#[derive(Serialize)]
+struct ChatReq<'a> {
+    model: &'a str,
+    messages: &'a [ChatMessage],
+    stream: bool,
+    options: &'a GenOptions,
+}
+
+#[derive(Deserialize)]
+struct ChatResp {
+    message: Option<ChatMessage>,
+    done: Option<bool>,
+    error: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ModelList {
+    #[serde(default)]
+    models: Vec<ModelEntry>,
+}
+
+#[derive(Deserialize)]
+struct ModelEntry {
+    name: String,
+    size: Option<u64>,
+    #[serde(default)]
+    details: ModelDetails,
+}
+
+#[derive(Deserialize, Default)]
+struct ModelDetails {
+    family: Option<String>,
+    parameter_size: Option<String>,
+    quantization_level: Option<String>,
+}
+
+trait Chunk {
+    fn text(self) -> Result<(Option<String>, bool), LlmError>;
+}
+
+impl Chunk for GenerateResp {
+    fn text(self) -> Result<(Option<String>, bool), LlmError> {
+        match self.error {
+            Some(err) => Err(LlmError::Upstream(format!("ollama: {}", err))),
+            None => Ok((self.response, self.done == Some(true))),
+        }
+    }
+}
+
+impl Chunk for ChatResp {
+    fn text(self) -> Result<(Option<String>, bool), LlmError> {
+        match self.error {
+            Some(err) => Err(LlmError::Upstream(format!("ollama: {}", err))),
+            None => Ok((self.message.map(|m| m.content), self.done == Some(true))),
+        }
+    }
+}
+
+pub struct Ollama {
+    base_url: String,
+    http: HttpClient,
+}
+
+impl Ollama {
+    pub fn new(base_url: impl Into<String>, http: HttpClient) -> Self {
+        Ollama { base_url: base_url.into().trim_end_matches('/').to_string(), http }
+    }
+
+    async fn post<B: Serialize>(&self, path: &str, body: &B) -> Result<reqwest::Response, LlmError> {
+        self.http.send(self.http.post(&format!("{}{}", self.base_url, path)).json(body)).await
+    }
+
+    async fn list(&self, path: &str) -> Result<Vec<ModelEntry>, LlmError> {
+        let resp = self.http.send_once(self.http.get(&format!("{}{}", self.base_url, path))).await?;
+        Ok(checked(resp).await?.json::<ModelList>().await?.models)
+    }
+}
+
+// Turns an error status into Ollama's `{"error": ..}` message before a
+// stream is read.
+async fn checked(resp: reqwest::Response) -> Result<reqwest::Response, LlmError> {
+    let status = resp.status();
+    if status.is_success() {
+        return Ok(resp);
+    }
+    let message = resp.json::<GenerateResp>().await.ok().and_then(|r| r.error).unwrap_or_else(|| status.to_string());
+    Err(LlmError::Upstream(format!("ollama: {}", message)))
+}
+
+#[async_trait]
+impl LlmProvider for Ollama {
+    fn name(&self) -> &'static str {
+        "ollama"
+    }
+
+    async fn generate(&self, model: &str, prompt: &str, options: &GenOptions) -> Result<String, LlmError> {
+        let body = GenerateReq { model, prompt, stream: false, options, format: None };
+        let resp: GenerateResp = self.post("/api/generate", &body).await?.json().await?;
+        completion(resp.text()?.0)
+    }
+
+    async fn generate_json(&self, model: &str, prompt: &str, schema: &serde_json::Value, options: &GenOptions) -> Result<String, LlmError> {
+        let body = GenerateReq { model, prompt, stream: false, options, format: Some(schema) };
+        let resp: GenerateResp = self.post("/api/generate", &body).await?.json().await?;
+        completion(resp.text()?.0)
+    }
+
+    async fn generate_stream(&self, model: &str, prompt: &str, options: &GenOptions) -> Result<TextStream, LlmError> {
+        let body = GenerateReq { model, prompt, stream: true, options, format: None };
+        let resp = checked(self.post("/api/generate", &body).await?).await?;
+        Ok(Box::pin(ndjson_fragments::<GenerateResp>(resp)))
+    }
+
+    async fn chat(&self, model: &str, messages: &[ChatMessage], options: &GenOptions) -> Result<String, LlmError> {
+        let body = ChatReq { model, messages, stream: false, options };
+        let resp: ChatResp = self.post("/api/chat", &body).await?.json().await?;
+        completion(resp.text()?.0)
+    }
+
+    async fn chat_stream(&self, model: &str, messages: &[ChatMessage], options: &GenOptions) -> Result<TextStream, LlmError> {
+        let body = ChatReq { model, messages, stream: true, options };
+        let resp = checked(self.post("/api/chat", &body).await?).await?;
+        Ok(Box::pin(ndjson_fragments::<ChatResp>(resp)))
+    }
+
+    // /api/tags lists what is pulled, /api/ps what is in memory.
+    async fn models(&self) -> Result<Vec<ModelInfo>, LlmError> {
+        let (installed, running) = futures_util::join!(self.list("/api/tags"), self.list("/api/ps"));
+        let running: Vec<String> = running?.into_iter().map(|m| m.name).collect();
+        Ok(installed?
+            .into_iter()
+            .map(|m| ModelInfo {
+                loaded: Some(running.contains(&m.name)),
+                name: m.name,
+                size: m.size,
+                family: m.details.family,
+                parameter_size: m.details.parameter_size,
+                quantization: m.details.quantization_level,
+            })
+            .collect())
+    }
+
+    // A generate request with no prompt only loads the model.
+    async fn warm(&self, model: &str) -> Result<(), LlmError> {
+        let resp = checked(self.post("/api/generate", &serde_json::json!({ "model": model })).await?).await?;
+        resp.json::<GenerateResp>().await?.text().map(|_| ())
+    }
 }
 
-pub async fn generate(model: &str, prompt: &str) -> Result<String, OllamaErr> {
-    let ollama_url = std::env::var("OLLAMA_URL")
-        .unwrap_or_else(|_| "http://127.0.0.1:11434".to_string());
-    let url = format!("{}/api/generate", ollama_url);
-    let client = Client::new();
-    let body = GenerateReq { model, prompt, stream: false, options: serde_json::json!({"temperature": 0.3, "num_ctx": 4096}) };
-    let resp: GenerateResp = client.post(&url).json(&body).send().await?.json().await?;
-    Ok(resp.response.unwrap_or_default())
+// Yields text fragments as Ollama produces them. Ollama streams one JSON
+// object per line; the last one carries `done: true`.
+fn ndjson_fragments<T: Chunk + DeserializeOwned>(resp: reqwest::Response) -> impl Stream<Item = Result<String, LlmError>> {
+    let lines = body_lines(resp);
+    try_stream! {
+        futures_util::pin_mut!(lines);
+        while let Some(line) = lines.next().await {
+            let (text, done) = serde_json::from_str::<T>(&line?)?.text()?;
+            if let Some(text) = text.filter(|t| !t.is_empty()) {
+                yield text;
+            }
+            if done {
+                break;
+            }
+        }
+    }
 }
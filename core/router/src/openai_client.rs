@@ -0,0 +1,198 @@
+use async_stream::try_stream;
+use async_trait::async_trait;
+use futures_util::{Stream, StreamExt};
+use reqwest::RequestBuilder;
+use serde::{Deserialize, Serialize};
+use crate::http_client::HttpClient;
+use crate::provider::{body_lines, completion, ChatMessage, LlmError, LlmProvider, ModelInfo, TextStream};
+use crate::tiers::GenOptions;
+
+// Client for local servers speaking the OpenAI chat completions protocol:
+// llama.cpp's llama-server, LM Studio, vLLM and the like.
+
+#[derive(Serialize)]
+struct CompletionReq<'a> {
+    model: &'a str,
+    messages: &'a [ChatMessage],
+    stream: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_p: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_tokens: Option<i32>,
+    #[serde(skip_serializing_if = "<[String]>::is_empty")]
+    stop: &'a [String],
+    #[serde(skip_serializing_if = "Option::is_none")]
+    seed: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    response_format: Option<serde_json::Value>,
+}
+
+impl<'a> CompletionReq<'a> {
+    // The context size is fixed when these servers load the model, so
+    // num_ctx has no equivalent; num_predict -1 means no max_tokens.
+    fn new(model: &'a str, messages: &'a [ChatMessage], options: &'a GenOptions, stream: bool) -> Self {
+        CompletionReq {
+            model,
+            messages,
+            stream,
+            temperature: options.temperature,
+            top_p: options.top_p,
+            max_tokens: options.num_predict.filter(|n| *n > 0),
+            stop: &options.stop,
+            seed: options.seed,
+            response_format: None,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct CompletionResp {
+    #[serde(default)]
+    choices: Vec<Choice>,
+    error: Option<ApiError>,
+}
+
+#[derive(Deserialize)]
+struct Choice {
+    message: Option<Delta>,
+    delta: Option<Delta>,
+}
+
+#[derive(Deserialize)]
+struct Delta {
+    content: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ModelList {
+    #[serde(default)]
+    data: Vec<ModelEntry>,
+}
+
+#[derive(Deserialize)]
+struct ModelEntry {
+    id: String,
+}
+
+// vLLM and LM Studio send `{"error": {"message": ..}}`; some llama.cpp
+// builds send a bare string.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ApiError {
+    Detailed { message: String },
+    Plain(String),
+}
+
+impl CompletionResp {
+    fn text(self) -> Result<Option<String>, LlmError> {
+        if let Some(err) = self.error {
+            let message = match err {
+                ApiError::Detailed { message } | ApiError::Plain(message) => message,
+            };
+            return Err(LlmError::Upstream(format!("openai-compatible server: {}", message)));
+        }
+        Ok(self
+            .choices
+            .into_iter()
+            .next()
+            .and_then(|c| c.message.or(c.delta))
+            .and_then(|d| d.content))
+    }
+}
+
+pub struct OpenAiCompatible {
+    base_url: String,
+    api_key: Option<String>,
+    http: HttpClient,
+}
+
+impl OpenAiCompatible {
+    pub fn new(base_url: impl Into<String>, api_key: Option<String>, http: HttpClient) -> Self {
+        let base_url = base_url.into();
+        let base_url = base_url.trim_end_matches('/').trim_end_matches("/v1").to_string();
+        OpenAiCompatible { base_url, api_key: api_key.filter(|k| !k.is_empty()), http }
+    }
+
+    fn completions(&self, body: &CompletionReq<'_>) -> RequestBuilder {
+        let req = self.http.post(&format!("{}/v1/chat/completions", self.base_url)).json(body);
+        match &self.api_key {
+            Some(key) => req.bearer_auth(key),
+            None => req,
+        }
+    }
+}
+
+#[async_trait]
+impl LlmProvider for OpenAiCompatible {
+    fn name(&self) -> &'static str {
+        "openai"
+    }
+
+    async fn chat(&self, model: &str, messages: &[ChatMessage], options: &GenOptions) -> Result<String, LlmError> {
+        let body = CompletionReq::new(model, messages, options, false);
+        let resp: CompletionResp = self.http.send(self.completions(&body)).await?.json().await?;
+        completion(resp.text()?)
+    }
+
+    // llama-server, LM Studio and vLLM all take the json_schema form.
+    async fn generate_json(&self, model: &str, prompt: &str, schema: &serde_json::Value, options: &GenOptions) -> Result<String, LlmError> {
+        let messages = [ChatMessage::new("user", prompt)];
+        let mut body = CompletionReq::new(model, &messages, options, false);
+        body.response_format = Some(serde_json::json!({
+            "type": "json_schema",
+            "json_schema": { "name": "result", "schema": schema },
+        }));
+        let resp: CompletionResp = self.http.send(self.completions(&body)).await?.json().await?;
+        completion(resp.text()?)
+    }
+
+    async fn chat_stream(&self, model: &str, messages: &[ChatMessage], options: &GenOptions) -> Result<TextStream, LlmError> {
+        let body = CompletionReq::new(model, messages, options, true);
+        let resp = self.http.send(self.completions(&body)).await?;
+        let status = resp.status();
+        if !status.is_success() {
+            return Err(match resp.json::<CompletionResp>().await.map(CompletionResp::text) {
+                Ok(Err(e)) => e,
+                _ => LlmError::Upstream(format!("openai-compatible server: {}", status)),
+            });
+        }
+        Ok(Box::pin(sse_fragments(resp)))
+    }
+
+    // /v1/models carries ids only, and whether a listed model is loaded
+    // differs between servers.
+    async fn models(&self) -> Result<Vec<ModelInfo>, LlmError> {
+        let mut req = self.http.get(&format!("{}/v1/models", self.base_url));
+        if let Some(key) = &self.api_key {
+            req = req.bearer_auth(key);
+        }
+        let resp = self.http.send_once(req).await?;
+        let status = resp.status();
+        if !status.is_success() {
+            return Err(LlmError::Upstream(format!("openai-compatible server: {}", status)));
+        }
+        let list: ModelList = resp.json().await?;
+        Ok(list.data.into_iter().map(|m| ModelInfo { name: m.id, ..ModelInfo::default() }).collect())
+    }
+}
+
+// Yields text fragments from a streamed completion. Each event is a
+// `data: {json}` line and the stream ends with `data: [DONE]`.
+fn sse_fragments(resp: reqwest::Response) -> impl Stream<Item = Result<String, LlmError>> {
+    let lines = body_lines(resp);
+    try_stream! {
+        futures_util::pin_mut!(lines);
+        while let Some(line) = lines.next().await {
+            let line = line?;
+            let Some(data) = line.strip_prefix("data:").map(str::trim) else { continue };
+            if data == "[DONE]" {
+                break;
+            }
+            if let Some(text) = serde_json::from_str::<CompletionResp>(data)?.text()?.filter(|t| !t.is_empty()) {
+                yield text;
+            }
+        }
+    }
+}
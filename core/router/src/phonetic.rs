@@ -0,0 +1,537 @@
+// Double Metaphone (Lawrence Philips), following the reference rules as
+// ported in Apache Commons Codec. Returns the primary and alternate keys,
+// each at most MAX_CODE_LEN characters.
+
+const MAX_CODE_LEN: usize = 4;
+
+struct Codes {
+    primary: String,
+    alternate: String,
+}
+
+impl Codes {
+    fn complete(&self) -> bool {
+        self.primary.len() >= MAX_CODE_LEN && self.alternate.len() >= MAX_CODE_LEN
+    }
+
+    fn push(&mut self, main: &str, alt: &str) {
+        push_limited(&mut self.primary, main);
+        push_limited(&mut self.alternate, alt);
+    }
+
+    fn both(&mut self, code: &str) {
+        self.push(code, code);
+    }
+
+    fn primary_only(&mut self, code: &str) {
+        push_limited(&mut self.primary, code);
+    }
+
+    fn alternate_only(&mut self, code: &str) {
+        push_limited(&mut self.alternate, code);
+    }
+}
+
+fn push_limited(target: &mut String, code: &str) {
+    for c in code.chars() {
+        if target.len() < MAX_CODE_LEN {
+            target.push(c);
+        }
+    }
+}
+
+struct Word {
+    chars: Vec<char>,
+    slavo_germanic: bool,
+}
+
+impl Word {
+    fn len(&self) -> isize {
+        self.chars.len() as isize
+    }
+
+    fn at(&self, i: isize) -> char {
+        if i < 0 {
+            return '\0';
+        }
+        self.chars.get(i as usize).copied().unwrap_or('\0')
+    }
+
+    fn has(&self, start: isize, options: &[&str]) -> bool {
+        let Some(len) = options.first().map(|o| o.chars().count() as isize) else { return false };
+        if start < 0 || start + len > self.len() {
+            return false;
+        }
+        let slice: String = self.chars[start as usize..(start + len) as usize].iter().collect();
+        options.contains(&slice.as_str())
+    }
+
+    fn vowel(&self, i: isize) -> bool {
+        matches!(self.at(i), 'A' | 'E' | 'I' | 'O' | 'U' | 'Y')
+    }
+
+    fn last(&self) -> isize {
+        self.len() - 1
+    }
+}
+
+pub fn double_metaphone(word: &str) -> (String, String) {
+    let upper: Vec<char> = word.trim().to_uppercase().chars().collect();
+    if upper.is_empty() {
+        return (String::new(), String::new());
+    }
+    let text: String = upper.iter().collect();
+    let w = Word {
+        slavo_germanic: text.contains('W') || text.contains('K') || text.contains("CZ") || text.contains("WITZ"),
+        chars: upper,
+    };
+    let mut codes = Codes { primary: String::new(), alternate: String::new() };
+    let mut i: isize = if w.has(0, &["GN", "KN", "PN", "WR", "PS"]) { 1 } else { 0 };
+
+    while !codes.complete() && i <= w.last() {
+        i = match w.at(i) {
+            'A' | 'E' | 'I' | 'O' | 'U' | 'Y' => {
+                if i == 0 {
+                    codes.both("A");
+                }
+                i + 1
+            }
+            'B' => {
+                codes.both("P");
+                if w.at(i + 1) == 'B' { i + 2 } else { i + 1 }
+            }
+            'Ç' => {
+                codes.both("S");
+                i + 1
+            }
+            'C' => handle_c(&w, &mut codes, i),
+            'D' => handle_d(&w, &mut codes, i),
+            'F' => {
+                codes.both("F");
+                if w.at(i + 1) == 'F' { i + 2 } else { i + 1 }
+            }
+            'G' => handle_g(&w, &mut codes, i),
+            'H' => {
+                if (i == 0 || w.vowel(i - 1)) && w.vowel(i + 1) {
+                    codes.both("H");
+                    i + 2
+                } else {
+                    i + 1
+                }
+            }
+            'J' => handle_j(&w, &mut codes, i),
+            'K' => {
+                codes.both("K");
+                if w.at(i + 1) == 'K' { i + 2 } else { i + 1 }
+            }
+            'L' => handle_l(&w, &mut codes, i),
+            'M' => {
+                codes.both("M");
+                let double = w.at(i + 1) == 'M'
+                    || (w.has(i - 1, &["UMB"]) && (i + 1 == w.last() || w.has(i + 2, &["ER"])));
+                if double { i + 2 } else { i + 1 }
+            }
+            'N' => {
+                codes.both("N");
+                if w.at(i + 1) == 'N' { i + 2 } else { i + 1 }
+            }
+            'Ñ' => {
+                codes.both("N");
+                i + 1
+            }
+            'P' => {
+                if w.at(i + 1) == 'H' {
+                    codes.both("F");
+                    i + 2
+                } else {
+                    codes.both("P");
+                    if w.has(i + 1, &["P", "B"]) { i + 2 } else { i + 1 }
+                }
+            }
+            'Q' => {
+                codes.both("K");
+                if w.at(i + 1) == 'Q' { i + 2 } else { i + 1 }
+            }
+            'R' => {
+                if i == w.last() && !w.slavo_germanic && w.has(i - 2, &["IE"]) && !w.has(i - 4, &["ME", "MA"]) {
+                    codes.alternate_only("R");
+                } else {
+                    codes.both("R");
+                }
+                if w.at(i + 1) == 'R' { i + 2 } else { i + 1 }
+            }
+            'S' => handle_s(&w, &mut codes, i),
+            'T' => handle_t(&w, &mut codes, i),
+            'V' => {
+                codes.both("F");
+                if w.at(i + 1) == 'V' { i + 2 } else { i + 1 }
+            }
+            'W' => handle_w(&w, &mut codes, i),
+            'X' => handle_x(&w, &mut codes, i),
+            'Z' => handle_z(&w, &mut codes, i),
+            _ => i + 1,
+        };
+    }
+    (codes.primary, codes.alternate)
+}
+
+fn handle_c(w: &Word, codes: &mut Codes, i: isize) -> isize {
+    let germanic_ch = w.has(i, &["CHIA"])
+        || (i > 1
+            && !w.vowel(i - 2)
+            && w.has(i - 1, &["ACH"])
+            && ((w.at(i + 2) != 'I' && w.at(i + 2) != 'E') || w.has(i - 2, &["BACHER", "MACHER"])));
+
+    if germanic_ch {
+        codes.both("K");
+        i + 2
+    } else if i == 0 && w.has(i, &["CAESAR"]) {
+        codes.both("S");
+        i + 2
+    } else if w.has(i, &["CH"]) {
+        handle_ch(w, codes, i)
+    } else if w.has(i, &["CZ"]) && !w.has(i - 2, &["WICZ"]) {
+        codes.push("S", "X");
+        i + 2
+    } else if w.has(i + 1, &["CIA"]) {
+        codes.both("X");
+        i + 3
+    } else if w.has(i, &["CC"]) && !(i == 1 && w.at(0) == 'M') {
+        if w.has(i + 2, &["I", "E", "H"]) && !w.has(i + 2, &["HU"]) {
+            if (i == 1 && w.at(i - 1) == 'A') || w.has(i - 1, &["UCCEE", "UCCES"]) {
+                codes.both("KS");
+            } else {
+                codes.both("X");
+            }
+            i + 3
+        } else {
+            codes.both("K");
+            i + 2
+        }
+    } else if w.has(i, &["CK", "CG", "CQ"]) {
+        codes.both("K");
+        i + 2
+    } else if w.has(i, &["CI", "CE", "CY"]) {
+        if w.has(i, &["CIO", "CIE", "CIA"]) {
+            codes.push("S", "X");
+        } else {
+            codes.both("S");
+        }
+        i + 2
+    } else {
+        codes.both("K");
+        if w.has(i + 1, &[" C", " Q", " G"]) {
+            i + 3
+        } else if w.has(i + 1, &["C", "K", "Q"]) && !w.has(i + 1, &["CE", "CI"]) {
+            i + 2
+        } else {
+            i + 1
+        }
+    }
+}
+
+fn handle_ch(w: &Word, codes: &mut Codes, i: isize) -> isize {
+    let greek_start = i == 0
+        && (w.has(i + 1, &["HARAC", "HARIS"]) || w.has(i + 1, &["HOR", "HYM", "HIA", "HEM"]))
+        && !w.has(0, &["CHORE"]);
+    let hard = w.has(0, &["VAN ", "VON "])
+        || w.has(0, &["SCH"])
+        || w.has(i - 2, &["ORCHES", "ARCHIT", "ORCHID"])
+        || w.has(i + 2, &["T", "S"])
+        || ((w.has(i - 1, &["A", "O", "U", "E"]) || i == 0)
+            && (w.has(i + 2, &["L", "R", "N", "M", "B", "H", "F", "V", "W", " "]) || i + 1 == w.last()));
+
+    if i > 0 && w.has(i, &["CHAE"]) {
+        codes.push("K", "X");
+    } else if greek_start || hard {
+        codes.both("K");
+    } else if i > 0 {
+        if w.has(0, &["MC"]) {
+            codes.both("K");
+        } else {
+            codes.push("X", "K");
+        }
+    } else {
+        codes.both("X");
+    }
+    i + 2
+}
+
+fn handle_d(w: &Word, codes: &mut Codes, i: isize) -> isize {
+    if w.has(i, &["DG"]) {
+        if w.has(i + 2, &["I", "E", "Y"]) {
+            codes.both("J");
+            i + 3
+        } else {
+            codes.both("TK");
+            i + 2
+        }
+    } else if w.has(i, &["DT", "DD"]) {
+        codes.both("T");
+        i + 2
+    } else {
+        codes.both("T");
+        i + 1
+    }
+}
+
+fn handle_g(w: &Word, codes: &mut Codes, i: isize) -> isize {
+    if w.at(i + 1) == 'H' {
+        return handle_gh(w, codes, i);
+    }
+    if w.at(i + 1) == 'N' {
+        if i == 1 && w.vowel(0) && !w.slavo_germanic {
+            codes.push("KN", "N");
+        } else if !w.has(i + 2, &["EY"]) && w.at(i + 1) != 'Y' && !w.slavo_germanic {
+            codes.push("N", "KN");
+        } else {
+            codes.both("KN");
+        }
+        return i + 2;
+    }
+    if w.has(i + 1, &["LI"]) && !w.slavo_germanic {
+        codes.push("KL", "L");
+        return i + 2;
+    }
+    if i == 0
+        && (w.at(i + 1) == 'Y'
+            || w.has(i + 1, &["ES", "EP", "EB", "EL", "EY", "IB", "IL", "IN", "IE", "EI", "ER"]))
+    {
+        codes.push("K", "J");
+        return i + 2;
+    }
+    if (w.has(i + 1, &["ER"]) || w.at(i + 1) == 'Y')
+        && !w.has(0, &["DANGER", "RANGER", "MANGER"])
+        && !w.has(i - 1, &["E", "I"])
+        && !w.has(i - 1, &["RGY", "OGY"])
+    {
+        codes.push("K", "J");
+        return i + 2;
+    }
+    if w.has(i + 1, &["E", "I", "Y"]) || w.has(i - 1, &["AGGI", "OGGI"]) {
+        if w.has(0, &["VAN ", "VON "]) || w.has(0, &["SCH"]) || w.has(i + 1, &["ET"]) {
+            codes.both("K");
+        } else if w.has(i + 1, &["IER"]) {
+            codes.both("J");
+        } else {
+            codes.push("J", "K");
+        }
+        return i + 2;
+    }
+    codes.both("K");
+    if w.at(i + 1) == 'G' { i + 2 } else { i + 1 }
+}
+
+fn handle_gh(w: &Word, codes: &mut Codes, i: isize) -> isize {
+    if i > 0 && !w.vowel(i - 1) {
+        codes.both("K");
+    } else if i == 0 {
+        codes.both(if w.at(i + 2) == 'I' { "J" } else { "K" });
+    } else if (i > 1 && w.has(i - 2, &["B", "H", "D"]))
+        || (i > 2 && w.has(i - 3, &["B", "H", "D"]))
+        || (i > 3 && w.has(i - 4, &["B", "H"]))
+    {
+        // silent, as in "bough" or "daughter"
+    } else if i > 2 && w.at(i - 1) == 'U' && w.has(i - 3, &["C", "G", "L", "R", "T"]) {
+        codes.both("F");
+    } else if i > 0 && w.at(i - 1) != 'I' {
+        codes.both("K");
+    }
+    i + 2
+}
+
+fn handle_j(w: &Word, codes: &mut Codes, i: isize) -> isize {
+    if w.has(i, &["JOSE"]) || w.has(0, &["SAN "]) {
+        if (i == 0 && w.at(i + 4) == ' ') || w.len() == 4 || w.has(0, &["SAN "]) {
+            codes.both("H");
+        } else {
+            codes.push("J", "H");
+        }
+        return i + 1;
+    }
+    if i == 0 {
+        codes.push("J", "A");
+    } else if w.vowel(i - 1) && !w.slavo_germanic && (w.at(i + 1) == 'A' || w.at(i + 1) == 'O') {
+        codes.push("J", "H");
+    } else if i == w.last() {
+        codes.primary_only("J");
+    } else if !w.has(i + 1, &["L", "T", "K", "S", "N", "M", "B", "Z"]) && !w.has(i - 1, &["S", "K", "L"]) {
+        codes.both("J");
+    }
+    if w.at(i + 1) == 'J' { i + 2 } else { i + 1 }
+}
+
+fn handle_l(w: &Word, codes: &mut Codes, i: isize) -> isize {
+    if w.at(i + 1) != 'L' {
+        codes.both("L");
+        return i + 1;
+    }
+    let spanish_ll = (i == w.len() - 3 && w.has(i - 1, &["ILLO", "ILLA", "ALLE"]))
+        || ((w.has(w.len() - 2, &["AS", "OS"]) || w.has(w.last(), &["A", "O"])) && w.has(i - 1, &["ALLE"]));
+    if spanish_ll {
+        codes.primary_only("L");
+    } else {
+        codes.both("L");
+    }
+    i + 2
+}
+
+fn handle_s(w: &Word, codes: &mut Codes, i: isize) -> isize {
+    if w.has(i - 1, &["ISL", "YSL"]) {
+        i + 1
+    } else if i == 0 && w.has(i, &["SUGAR"]) {
+        codes.push("X", "S");
+        i + 1
+    } else if w.has(i, &["SH"]) {
+        if w.has(i + 1, &["HEIM", "HOEK", "HOLM", "HOLZ"]) {
+            codes.both("S");
+        } else {
+            codes.both("X");
+        }
+        i + 2
+    } else if w.has(i, &["SIO", "SIA"]) || w.has(i, &["SIAN"]) {
+        if w.slavo_germanic {
+            codes.both("S");
+        } else {
+            codes.push("S", "X");
+        }
+        i + 3
+    } else if (i == 0 && w.has(i + 1, &["M", "N", "L", "W"])) || w.has(i + 1, &["Z"]) {
+        codes.push("S", "X");
+        if w.has(i + 1, &["Z"]) { i + 2 } else { i + 1 }
+    } else if w.has(i, &["SC"]) {
+        if w.at(i + 2) == 'H' {
+            if w.has(i + 3, &["OO", "ER", "EN", "UY", "ED", "EM"]) {
+                if w.has(i + 3, &["ER", "EN"]) {
+                    codes.push("X", "SK");
+                } else {
+                    codes.both("SK");
+                }
+            } else if i == 0 && !w.vowel(3) && w.at(3) != 'W' {
+                codes.push("X", "S");
+            } else {
+                codes.both("X");
+            }
+        } else if w.has(i + 2, &["I", "E", "Y"]) {
+            codes.both("S");
+        } else {
+            codes.both("SK");
+        }
+        i + 3
+    } else {
+        if i == w.last() && w.has(i - 2, &["AI", "OI"]) {
+            codes.alternate_only("S");
+        } else {
+            codes.both("S");
+        }
+        if w.has(i + 1, &["S", "Z"]) { i + 2 } else { i + 1 }
+    }
+}
+
+fn handle_t(w: &Word, codes: &mut Codes, i: isize) -> isize {
+    if w.has(i, &["TION"]) || w.has(i, &["TIA", "TCH"]) {
+        codes.both("X");
+        i + 3
+    } else if w.has(i, &["TH"]) || w.has(i, &["TTH"]) {
+        if w.has(i + 2, &["OM", "AM"]) || w.has(0, &["VAN ", "VON "]) || w.has(0, &["SCH"]) {
+            codes.both("T");
+        } else {
+            codes.push("0", "T");
+        }
+        i + 2
+    } else {
+        codes.both("T");
+        if w.has(i + 1, &["T", "D"]) { i + 2 } else { i + 1 }
+    }
+}
+
+fn handle_w(w: &Word, codes: &mut Codes, i: isize) -> isize {
+    if w.has(i, &["WR"]) {
+        codes.both("R");
+        return i + 2;
+    }
+    if i == 0 && (w.vowel(i + 1) || w.has(i, &["WH"])) {
+        if w.vowel(i + 1) {
+            codes.push("A", "F");
+        } else {
+            codes.both("A");
+        }
+        i + 1
+    } else if (i == w.last() && w.vowel(i - 1))
+        || w.has(i - 1, &["EWSKI", "EWSKY", "OWSKI", "OWSKY"])
+        || w.has(0, &["SCH"])
+    {
+        codes.alternate_only("F");
+        i + 1
+    } else if w.has(i, &["WICZ", "WITZ"]) {
+        codes.push("TS", "FX");
+        i + 4
+    } else {
+        i + 1
+    }
+}
+
+fn handle_x(w: &Word, codes: &mut Codes, i: isize) -> isize {
+    if i == 0 {
+        codes.both("S");
+        return i + 1;
+    }
+    let silent_french = i == w.last() && (w.has(i - 3, &["IAU", "EAU"]) || w.has(i - 2, &["AU", "OU"]));
+    if !silent_french {
+        codes.both("KS");
+    }
+    if w.has(i + 1, &["C", "X"]) { i + 2 } else { i + 1 }
+}
+
+fn handle_z(w: &Word, codes: &mut Codes, i: isize) -> isize {
+    if w.at(i + 1) == 'H' {
+        codes.both("J");
+        return i + 2;
+    }
+    if w.has(i + 1, &["ZO", "ZI", "ZA"]) || (w.slavo_germanic && i > 0 && w.at(i - 1) != 'T') {
+        codes.push("S", "TS");
+    } else {
+        codes.both("S");
+    }
+    if w.at(i + 1) == 'Z' { i + 2 } else { i + 1 }
+}
+
+const KEYBOARD_ROWS: [(&str, f32); 3] = [("qwertyuiop", 0.0), ("asdfghjkl", 0.25), ("zxcvbnm", 0.75)];
+
+fn key_position(c: char) -> Option<(f32, f32)> {
+    KEYBOARD_ROWS.iter().enumerate().find_map(|(row, (keys, offset))| {
+        keys.find(c).map(|col| (col as f32 + offset, row as f32))
+    })
+}
+
+// Neighbouring keys on a QWERTY layout, e.g. 's' touches 'a', 'w', 'e', 'd', 'z' and 'x'.
+pub fn keys_adjacent(a: char, b: char) -> bool {
+    match (key_position(a), key_position(b)) {
+        (Some((ax, ay)), Some((bx, by))) if a != b => ((ax - bx).powi(2) + (ay - by).powi(2)).sqrt() < 1.3,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::double_metaphone;
+
+    #[test]
+    fn reference_keys() {
+        let cases = [
+            // TH before OM is a hard T. PH sounds as F and an initial PS or PN
+            // drops the P, but the P in MPS is sounded.
+            ("Thompson", "TMPS", "TMPS"),
+            ("Schmidt", "XMT", "SMT"),
+            ("psychology", "SXLJ", "SKLK"),
+            ("Smith", "SM0", "XMT"),
+            ("knight", "NT", "NT"),
+            ("Caesar", "SSR", "SSR"),
+            ("church", "XRX", "XRK"),
+            ("", "", ""),
+        ];
+        for (word, primary, alternate) in cases {
+            assert_eq!(double_metaphone(word), (primary.to_string(), alternate.to_string()), "{}", word);
+        }
+    }
+}
@@ -0,0 +1,164 @@
+use async_stream::try_stream;
+use async_trait::async_trait;
+use futures_util::stream::BoxStream;
+use futures_util::{Stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use thiserror::Error;
+use crate::http_client::HttpClient;
+use crate::mock_client::MockProvider;
+use crate::ollama_client::Ollama;
+use crate::openai_client::OpenAiCompatible;
+use crate::tiers::GenOptions;
+
+#[derive(Error, Debug)]
+pub enum LlmError {
+    #[error("http: {0}")]
+    Http(reqwest::Error),
+    #[error("timed out waiting for the model server")]
+    Timeout,
+    #[error("model server unavailable: {reason} (tried {attempts}x)")]
+    Unavailable { attempts: u32, reason: String },
+    #[error("model returned empty response")]
+    Empty,
+    #[error("malformed response: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("{0}")]
+    Upstream(String),
+}
+
+impl From<reqwest::Error> for LlmError {
+    fn from(e: reqwest::Error) -> Self {
+        if e.is_timeout() {
+            LlmError::Timeout
+        } else {
+            LlmError::Http(e)
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChatMessage {
+    pub role: String,
+    pub content: String,
+}
+
+impl ChatMessage {
+    pub fn new(role: &str, content: impl Into<String>) -> Self {
+        ChatMessage { role: role.to_string(), content: content.into() }
+    }
+}
+
+pub type TextStream = BoxStream<'static, Result<String, LlmError>>;
+
+// An installed model. Backends fill in what they know; `loaded` is None
+// when the backend cannot tell whether the model is in memory.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct ModelInfo {
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub size: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub family: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub parameter_size: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub quantization: Option<String>,
+    pub loaded: Option<bool>,
+}
+
+// A text generation backend. `generate` is a single prompt with no roles;
+// backends without a raw completion endpoint send it as one user message.
+// Options a backend has no equivalent for are ignored.
+#[async_trait]
+pub trait LlmProvider: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    async fn chat(&self, model: &str, messages: &[ChatMessage], options: &GenOptions) -> Result<String, LlmError>;
+
+    async fn chat_stream(&self, model: &str, messages: &[ChatMessage], options: &GenOptions) -> Result<TextStream, LlmError>;
+
+    async fn generate(&self, model: &str, prompt: &str, options: &GenOptions) -> Result<String, LlmError> {
+        self.chat(model, &[ChatMessage::new("user", prompt)], options).await
+    }
+
+    async fn generate_stream(&self, model: &str, prompt: &str, options: &GenOptions) -> Result<TextStream, LlmError> {
+        self.chat_stream(model, &[ChatMessage::new("user", prompt)], options).await
+    }
+
+    // `generate` with the reply constrained to JSON matching `schema`.
+    // Backends that cannot constrain output rely on the prompt asking for it.
+    async fn generate_json(&self, model: &str, prompt: &str, _schema: &serde_json::Value, options: &GenOptions) -> Result<String, LlmError> {
+        self.generate(model, prompt, options).await
+    }
+
+    // Installed models. Listing is not retried, so a backend that is down
+    // is reported quickly.
+    async fn models(&self) -> Result<Vec<ModelInfo>, LlmError>;
+
+    // Loads the model into memory so the next request does not wait for it.
+    // Without a dedicated endpoint this is a one-token generation.
+    async fn warm(&self, model: &str) -> Result<(), LlmError> {
+        let options = GenOptions { num_predict: Some(1), ..GenOptions::default() };
+        match self.generate(model, "Hi", &options).await {
+            Ok(_) | Err(LlmError::Empty) => Ok(()),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+// Picks the backend from LLM_PROVIDER ("ollama" by default, or "openai" for
+// llama.cpp's llama-server, LM Studio, vLLM and other servers speaking
+// /v1/chat/completions). LLM_BASE_URL overrides the server address and
+// LLM_API_KEY is sent as a bearer token when set. "mock" answers in-process
+// without any model, see mock_client.
+pub fn from_env(http: HttpClient) -> Arc<dyn LlmProvider> {
+    let kind = std::env::var("LLM_PROVIDER").unwrap_or_default().to_lowercase();
+    let base_url = std::env::var("LLM_BASE_URL").ok().filter(|u| !u.is_empty());
+    match kind.as_str() {
+        "openai" | "llamacpp" | "llama.cpp" | "lmstudio" | "vllm" => {
+            let base_url = base_url.unwrap_or_else(|| "http://127.0.0.1:8080".to_string());
+            Arc::new(OpenAiCompatible::new(base_url, std::env::var("LLM_API_KEY").ok(), http))
+        }
+        "mock" => Arc::new(MockProvider::from_env()),
+        other => {
+            if !other.is_empty() && other != "ollama" {
+                tracing::warn!("unknown LLM_PROVIDER {:?}, using ollama", other);
+            }
+            let base_url = base_url
+                .or_else(|| std::env::var("OLLAMA_URL").ok())
+                .unwrap_or_else(|| "http://127.0.0.1:11434".to_string());
+            Arc::new(Ollama::new(base_url, http))
+        }
+    }
+}
+
+// The text of a finished completion; `Empty` when the server sent none or
+// only whitespace.
+pub(crate) fn completion(text: Option<String>) -> Result<String, LlmError> {
+    text.filter(|t| !t.trim().is_empty()).ok_or(LlmError::Empty)
+}
+
+// Splits a streamed response body into lines, blank lines dropped. Both the
+// NDJSON Ollama streams and the SSE OpenAI-compatible servers send are
+// line oriented.
+pub(crate) fn body_lines(resp: reqwest::Response) -> impl Stream<Item = Result<String, LlmError>> {
+    let mut bytes = resp.bytes_stream();
+    try_stream! {
+        let mut buf: Vec<u8> = Vec::new();
+        while let Some(chunk) = bytes.next().await {
+            buf.extend_from_slice(&chunk?);
+            while let Some(pos) = buf.iter().position(|&b| b == b'\n') {
+                let line: Vec<u8> = buf.drain(..=pos).collect();
+                let line = String::from_utf8_lossy(&line).trim().to_string();
+                if !line.is_empty() {
+                    yield line;
+                }
+            }
+        }
+        let rest = String::from_utf8_lossy(&buf).trim().to_string();
+        if !rest.is_empty() {
+            yield rest;
+        }
+    }
+}
@@ -0,0 +1,142 @@
+use axum::{extract::State, Json};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use crate::diff::{self, TextEdit};
+use crate::error::{ApiJson, RouterError};
+use crate::minor_edit::EditItem;
+use crate::AppState;
+
+// Moves copy edits made against an older text onto the text as it is now,
+// for authors who keep writing while a slow model works. The router
+// remembers the texts it recently edited by hash; what the author changed
+// since is diffed from that base, and each edit is shifted past those
+// changes unless they touch the same words. Those edits come back as
+// conflicts and the author's writing stays as it is.
+
+// Texts remembered for rebasing; older ones have to be sent as `base`.
+const KEPT_BASES: usize = 32;
+
+#[derive(Default)]
+pub struct BaseTexts {
+    texts: Mutex<VecDeque<(String, Arc<str>)>>,
+}
+
+impl BaseTexts {
+    // Remembers `text` and returns its hash.
+    pub fn keep(&self, text: &str) -> String {
+        let hash = text_hash(text);
+        let mut texts = self.texts.lock().unwrap();
+        texts.retain(|(h, _)| *h != hash);
+        if texts.len() == KEPT_BASES {
+            texts.pop_front();
+        }
+        texts.push_back((hash.clone(), text.into()));
+        hash
+    }
+
+    fn get(&self, hash: &str) -> Option<Arc<str>> {
+        self.texts.lock().unwrap().iter().find(|(h, _)| h == hash).map(|(_, text)| text.clone())
+    }
+}
+
+// 64-bit FNV-1a of the text's UTF-8 bytes, in hex. Stable across builds, so
+// clients can compute it too.
+pub fn text_hash(text: &str) -> String {
+    let hash = text.bytes().fold(0xcbf29ce484222325u64, |h, b| (h ^ b as u64).wrapping_mul(0x100000001b3));
+    format!("{:016x}", hash)
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RebaseReq {
+    base_hash: String,
+    // The text the edits were made against; only needed once the router
+    // has forgotten it.
+    base: Option<String>,
+    // The text as it is now.
+    text: String,
+    edits: Vec<EditItem>,
+}
+
+// Where an edit's words are in the current text, and what they read now.
+#[derive(Debug, Serialize)]
+pub struct Current {
+    start: usize,
+    end: usize,
+    text: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Conflict {
+    // The edit as sent, against the base text.
+    #[serde(flatten)]
+    edit: EditItem,
+    current: Current,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RebaseResp {
+    // Hash of the current text, which the rebased edits now refer to.
+    base_hash: String,
+    edits: Vec<EditItem>,
+    conflicts: Vec<Conflict>,
+}
+
+// Whether the author's change touches the same words as the edit. Two
+// insertions at the same point conflict too, as their order is unknown.
+fn overlaps(author: &TextEdit, edit: &EditItem) -> bool {
+    (author.start < edit.end && edit.start < author.end) || (author.start == author.end && edit.start == edit.end && author.start == edit.start)
+}
+
+fn growth(author: &TextEdit) -> i64 {
+    author.new.chars().count() as i64 - (author.end - author.start) as i64
+}
+
+fn rebase(base: &str, text: &str, edits: Vec<EditItem>) -> (Vec<EditItem>, Vec<Conflict>) {
+    let author = diff::edits(base, text);
+    let current: Vec<char> = text.chars().collect();
+    let mut rebased = Vec::new();
+    let mut conflicts = Vec::new();
+    for mut edit in edits {
+        let touching: Vec<&TextEdit> = author.iter().filter(|a| overlaps(a, &edit)).collect();
+        let before: i64 = author.iter().filter(|a| a.end <= edit.start && !overlaps(a, &edit)).map(growth).sum();
+        if touching.is_empty() {
+            let start = (edit.start as i64 + before) as usize;
+            edit.end = start + (edit.end - edit.start);
+            edit.start = start;
+            edit.line = 1 + current[..start].iter().filter(|c| **c == '\n').count();
+            rebased.push(edit);
+        } else {
+            let lo = touching.iter().map(|a| a.start).chain([edit.start]).min().unwrap();
+            let hi = touching.iter().map(|a| a.end).chain([edit.end]).max().unwrap();
+            let start = (lo as i64 + before) as usize;
+            let end = (hi as i64 + before + touching.iter().map(|a| growth(a)).sum::<i64>()) as usize;
+            let current = Current { start, end, text: current[start..end].iter().collect() };
+            conflicts.push(Conflict { edit, current });
+        }
+    }
+    (rebased, conflicts)
+}
+
+pub async fn rebase_edits(State(state): State<AppState>, ApiJson(req): ApiJson<RebaseReq>) -> Result<Json<RebaseResp>, RouterError> {
+    let base: Arc<str> = match req.base {
+        Some(base) if text_hash(&base) == req.base_hash => base.into(),
+        Some(_) => return Err(RouterError::BadInput("'base' does not match 'base_hash'".into())),
+        None => state.bases.get(&req.base_hash).ok_or_else(|| {
+            RouterError::NotFound(format!("no text with hash {} is remembered; send it as 'base'", req.base_hash))
+        })?,
+    };
+    let chars: Vec<char> = base.chars().collect();
+    for (i, edit) in req.edits.iter().enumerate() {
+        let found = chars.get(edit.start..edit.end).map(|c| c.iter().collect::<String>());
+        if found.as_deref() != Some(edit.old.as_str()) {
+            return Err(RouterError::BadInput(format!("edit {} does not match the base text", i)));
+        }
+    }
+
+    let (edits, conflicts) = rebase(&base, &req.text, req.edits);
+    if !conflicts.is_empty() {
+        tracing::info!("{} edits conflict with the author's changes", conflicts.len());
+    }
+    Ok(Json(RebaseResp { base_hash: state.bases.keep(&req.text), edits, conflicts }))
+}
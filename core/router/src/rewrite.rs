@@ -0,0 +1,163 @@
+use axum::{extract::State, Json};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use crate::error::{require, ApiJson, RouterError};
+use crate::guards::Change;
+use crate::scheduler::{RouteReq, ServedBy, TaskKind};
+use crate::tiers::{GenOptions, Tier};
+use crate::verify::{self, KindDelta};
+use crate::{micro_edit, minor_edit, AppState};
+
+// Alternative wordings for a passage, one per intent, each scored by the
+// heuristics in its paragraph so the author can compare them.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Intent {
+    Tighter,
+    MoreVivid,
+    MoreTension,
+    Simpler,
+}
+
+impl Intent {
+    const ALL: [Intent; 4] = [Intent::Tighter, Intent::MoreVivid, Intent::MoreTension, Intent::Simpler];
+
+    fn goal(self) -> &'static str {
+        match self {
+            Intent::Tighter => "Tighter: cut every word the passage can do without.",
+            Intent::MoreVivid => "More vivid: concrete, sensory detail and specific verbs.",
+            Intent::MoreTension => "More tension: sharpen the conflict, stakes or unease.",
+            Intent::Simpler => "Simpler: plain words and short, clear sentences.",
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RewriteReq {
+    #[serde(default)]
+    model: String,
+    tier: Option<Tier>,
+    options: Option<GenOptions>,
+    request_id: Option<String>,
+    paragraph: String,
+    // Character range of the passage to rewrite within `paragraph`.
+    start: usize,
+    end: usize,
+    // One alternative per intent; all four when left out.
+    intents: Option<Vec<Intent>>,
+    project: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Scores {
+    // Heuristics issues in the paragraph with this wording.
+    issues: usize,
+    // Against the original wording; lower is better.
+    delta: i64,
+    by_kind: BTreeMap<String, KindDelta>,
+    // New errors, such as an em dash or a misspelling.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    introduces: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Alternative {
+    intent: Intent,
+    text: String,
+    scores: Scores,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RewriteResp {
+    request_id: String,
+    // Intents whose rewrite was empty or left the passage as it was are
+    // left out.
+    alternatives: Vec<Alternative>,
+    #[serde(flatten)]
+    served_by: ServedBy,
+}
+
+fn rewrite_prompt(paragraph: &str, passage: &str, intent: Intent) -> String {
+    format!(
+        "SYSTEM: You are a fiction line editor offering an author alternatives for one passage.
+
+GOAL:
+{}
+
+PARAGRAPH (for context only):
+{}
+
+INSTRUCTIONS:
+Rewrite only the passage below toward the goal, keeping its meaning, tense, point of view and the author's voice. It must read naturally in its place in the paragraph. Return ONLY the rewritten passage—no comments, no quotes around it.
+
+PASSAGE:
+{}",
+        intent.goal(),
+        paragraph,
+        passage
+    )
+}
+
+fn score(paragraph: &str, user_words: &HashSet<String>, change: Change) -> Scores {
+    let mut report = verify::verify(paragraph, user_words, &[change]);
+    Scores {
+        issues: report.delta.values().map(|k| k.after).sum(),
+        delta: report.delta.values().map(|k| k.delta).sum(),
+        by_kind: report.delta,
+        introduces: report.introduced.remove(0),
+    }
+}
+
+pub async fn rewrite(State(state): State<AppState>, ApiJson(req): ApiJson<RewriteReq>) -> Result<Json<RewriteResp>, RouterError> {
+    require(&req.paragraph, "paragraph")?;
+    let chars: Vec<char> = req.paragraph.chars().collect();
+    if req.start >= req.end || req.end > chars.len() {
+        return Err(RouterError::BadInput(format!("'start'..'end' must be a non-empty range within the paragraph's {} characters", chars.len())));
+    }
+    let passage: String = chars[req.start..req.end].iter().collect();
+    let intents = match req.intents.as_deref() {
+        Some([]) => return Err(RouterError::BadInput("'intents' must not be empty".into())),
+        // One model call per intent, so at most one of each.
+        Some(intents) if intents.iter().enumerate().any(|(i, intent)| intents[..i].contains(intent)) => {
+            return Err(RouterError::BadInput("'intents' must not repeat an intent".into()))
+        }
+        Some(intents) => intents.to_vec(),
+        None => Intent::ALL.to_vec(),
+    };
+
+    let route = RouteReq {
+        task: TaskKind::Rewrite,
+        input_size: chars.len(),
+        tier: req.tier,
+        model: &req.model,
+        options: req.options.as_ref(),
+    };
+    let routed = state.scheduler.route(state.llm.as_ref(), &state.tiers, route).await?;
+    let prompts: Vec<String> = intents.iter().map(|intent| rewrite_prompt(&req.paragraph, &passage, *intent)).collect();
+
+    let job = state.jobs.start(req.request_id.clone());
+    let outcome = job.run(micro_edit::rewrite_all(state.llm.as_ref(), routed.model(), &routed.options, &prompts)).await;
+    let request_id = job.id.clone();
+    job.finish();
+    let rewrites = outcome??;
+
+    let user_words = state.user_dicts.for_project(req.project.as_deref());
+    let alternatives = intents
+        .into_iter()
+        .zip(&rewrites)
+        .filter_map(|(intent, rewrite)| {
+            let cleaned = micro_edit::clean_rewrite(&passage, rewrite);
+            if cleaned.is_empty() {
+                return None;
+            }
+            let text = minor_edit::keep_margins(&passage, cleaned);
+            if text == passage {
+                return None;
+            }
+            let scores = score(&req.paragraph, &user_words, Change { start: req.start, end: req.end, new: &text });
+            Some(Alternative { intent, text, scores })
+        })
+        .collect();
+    Ok(Json(RewriteResp { request_id, alternatives, served_by: routed.served_by }))
+}
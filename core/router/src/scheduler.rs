@@ -1,2 +1,148 @@
-// Placeholder for fast/heavy queues and typing-aware throttling.
-// In a later pass, promote requests into lanes and enforce fairness.
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use crate::chunking::CHARS_PER_TOKEN;
+use crate::error::RouterError;
+use crate::provider::LlmProvider;
+use crate::tiers::{same_model, GenOptions, Tier, Tiers};
+
+// Picks the model tier for a request from what it asks for and how much
+// text it carries, then falls back to a smaller tier when the preferred
+// model is not installed. Fast/heavy queues and typing-aware throttling
+// would also live here.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TaskKind {
+    Chat,
+    CopyEdit,
+    Rewrite,
+    Summarize,
+}
+
+impl TaskKind {
+    fn preferred_tier(self) -> Tier {
+        match self {
+            TaskKind::Chat | TaskKind::Summarize => Tier::Small,
+            TaskKind::CopyEdit => Tier::Medium,
+            TaskKind::Rewrite => Tier::Large,
+        }
+    }
+}
+
+pub struct RouteReq<'a> {
+    pub task: TaskKind,
+    // Characters of text the model will read.
+    pub input_size: usize,
+    pub tier: Option<Tier>,
+    // An explicit model skips routing altogether.
+    pub model: &'a str,
+    pub options: Option<&'a GenOptions>,
+}
+
+// Which model served a request, reported back to the client.
+#[derive(Debug, Clone, Serialize)]
+pub struct ServedBy {
+    pub model: String,
+    pub tier: Tier,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fallback_from: Option<String>,
+}
+
+pub struct Routed {
+    pub served_by: ServedBy,
+    pub options: GenOptions,
+}
+
+impl Routed {
+    pub fn model(&self) -> &str {
+        &self.served_by.model
+    }
+}
+
+// How long the list of installed models is trusted before asking the
+// backend again.
+const CATALOG_TTL: Duration = Duration::from_secs(30);
+
+#[derive(Default)]
+pub struct Scheduler {
+    installed: Mutex<Option<(Instant, Vec<String>)>>,
+}
+
+impl Scheduler {
+    pub async fn route(&self, llm: &dyn LlmProvider, tiers: &Tiers, req: RouteReq<'_>) -> Result<Routed, RouterError> {
+        if !req.model.trim().is_empty() {
+            let (tier, model, options) = tiers.resolve(req.tier, req.model, req.options)?;
+            return Ok(Routed { served_by: ServedBy { model, tier, fallback_from: None }, options });
+        }
+
+        let preferred = req.tier.unwrap_or_else(|| fitting_tier(tiers, req.task.preferred_tier(), req.input_size));
+        let tier = match self.installed(llm).await {
+            Some(installed) => {
+                let is_installed = |t: Tier| installed.iter().any(|m| same_model(m, &tiers.get(t).model));
+                let smaller: Vec<Tier> = Tier::ALL.into_iter().rev().filter(|t| *t < preferred && is_installed(*t)).collect();
+                if is_installed(preferred) || smaller.is_empty() {
+                    preferred
+                } else {
+                    // Stepping down must not trade a missing model for one whose
+                    // context window cannot hold the input.
+                    smaller.into_iter().find(|t| fits(tiers, *t, req.input_size)).ok_or_else(|| {
+                        RouterError::BadInput(format!(
+                            "{} is not installed and no smaller installed model's context can hold {} characters of input",
+                            tiers.get(preferred).model,
+                            req.input_size
+                        ))
+                    })?
+                }
+            }
+            None => preferred,
+        };
+
+        let model = tiers.get(tier).model.clone();
+        let fallback_from = (tier != preferred).then(|| tiers.get(preferred).model.clone());
+        if let Some(missing) = &fallback_from {
+            tracing::info!("{} is not installed, using {} instead", missing, model);
+        }
+        let options = tiers.options(tier, req.options)?;
+        Ok(Routed { served_by: ServedBy { model, tier, fallback_from }, options })
+    }
+
+    // Installed model names, or None when the backend cannot say. A failed
+    // lookup is not cached so the next request asks again.
+    async fn installed(&self, llm: &dyn LlmProvider) -> Option<Vec<String>> {
+        if let Some((at, names)) = self.installed.lock().unwrap().as_ref() {
+            if at.elapsed() < CATALOG_TTL {
+                return Some(names.clone());
+            }
+        }
+        let names: Vec<String> = llm.models().await.ok()?.into_iter().map(|m| m.name).collect();
+        // A backend that lists nothing (e.g. the mock) serves whatever is asked.
+        if names.is_empty() {
+            return None;
+        }
+        *self.installed.lock().unwrap() = Some((Instant::now(), names.clone()));
+        Some(names)
+    }
+}
+
+// Whether the tier's context window holds the input plus the reply.
+fn fits(tiers: &Tiers, tier: Tier, input_size: usize) -> bool {
+    let options = &tiers.get(tier).options;
+    let reply = options.num_predict.filter(|n| *n > 0).unwrap_or(0) as usize;
+    options.num_ctx.is_none_or(|ctx| input_size / CHARS_PER_TOKEN + reply <= ctx as usize)
+}
+
+// The smallest tier at or above `tier` that fits the input; the largest
+// window when none does.
+fn fitting_tier(tiers: &Tiers, tier: Tier, input_size: usize) -> Tier {
+    Tier::ALL
+        .into_iter()
+        .filter(|t| *t >= tier)
+        .find(|t| fits(tiers, *t, input_size))
+        .unwrap_or_else(|| {
+            Tier::ALL
+                .into_iter()
+                .max_by_key(|t| tiers.get(*t).options.num_ctx.unwrap_or(u32::MAX))
+                .unwrap_or(tier)
+        })
+}
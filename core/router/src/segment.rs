@@ -0,0 +1,224 @@
+/// Byte range into the analyzed text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn contains(&self, pos: usize) -> bool {
+        pos >= self.start && pos < self.end
+    }
+}
+
+const ABBREVIATIONS: &[&str] = &[
+    "mr", "mrs", "ms", "dr", "st", "jr", "sr", "prof", "vs", "etc", "e.g", "i.e",
+    "mt", "capt", "lt", "sgt", "gen", "col", "rev", "no",
+];
+
+/// Abbreviations that only count as such before a number, as in "No. 5";
+/// "I said no. Then..." still ends the sentence.
+const NUMBER_ABBREVIATIONS: &[&str] = &["no"];
+
+fn is_terminal(c: char) -> bool {
+    matches!(c, '.' | '!' | '?')
+}
+
+fn is_closing(c: char) -> bool {
+    matches!(c, '"' | '\'' | '”' | '’' | ')' | ']')
+}
+
+fn is_abbreviation(text: &str, dot: usize) -> bool {
+    let word_start = text[..dot]
+        .rfind(|c: char| !(c.is_alphabetic() || c == '.'))
+        .map(|p| p + text[p..].chars().next().map_or(1, char::len_utf8))
+        .unwrap_or(0);
+    let word = text[word_start..dot].to_lowercase();
+    if NUMBER_ABBREVIATIONS.contains(&word.as_str()) {
+        return text[dot + 1..].trim_start().starts_with(|c: char| c.is_ascii_digit());
+    }
+    ABBREVIATIONS.contains(&word.as_str())
+}
+
+/// Splits text into sentences. A line break always ends a sentence; terminal
+/// punctuation followed by a closing quote and a lowercase word is treated as
+/// a dialogue tag continuation ("Where?" she asked.) rather than a boundary.
+pub fn sentences(text: &str) -> Vec<Span> {
+    let chars: Vec<(usize, char)> = text.char_indices().collect();
+    let byte_after = |i: usize| chars.get(i + 1).map_or(text.len(), |&(p, _)| p);
+    let mut spans = Vec::new();
+    let mut start: Option<usize> = None;
+    let mut i = 0;
+
+    while i < chars.len() {
+        let (pos, c) = chars[i];
+
+        if c == '\n' {
+            if let Some(s) = start.take() {
+                spans.push(Span { start: s, end: s + text[s..pos].trim_end().len() });
+            }
+            i += 1;
+            continue;
+        }
+        if start.is_none() {
+            if !c.is_whitespace() {
+                start = Some(pos);
+            }
+            i += 1;
+            continue;
+        }
+        if !is_terminal(c) {
+            i += 1;
+            continue;
+        }
+
+        let mut j = i;
+        while j + 1 < chars.len() && is_terminal(chars[j + 1].1) {
+            j += 1;
+        }
+        let ellipsis = chars[i..=j].iter().filter(|&&(_, c)| c == '.').count() >= 3;
+        let mut k = j;
+        let mut closed_quote = false;
+        while k + 1 < chars.len() && is_closing(chars[k + 1].1) {
+            k += 1;
+            closed_quote = true;
+        }
+        if k + 1 < chars.len() && !chars[k + 1].1.is_whitespace() {
+            i = k + 1;
+            continue;
+        }
+
+        let next = chars[k + 1..].iter().map(|&(_, c)| c).find(|c| !c.is_whitespace() || *c == '\n');
+        let boundary = match next {
+            None | Some('\n') => true,
+            Some(n) if ellipsis => n.is_uppercase(),
+            Some(_) if c == '.' && j == i && is_abbreviation(text, pos) => false,
+            Some(n) if closed_quote => !n.is_lowercase(),
+            Some(_) => true,
+        };
+        if boundary {
+            if let Some(s) = start.take() {
+                spans.push(Span { start: s, end: byte_after(k) });
+            }
+        }
+        i = k + 1;
+    }
+
+    if let Some(s) = start {
+        spans.push(Span { start: s, end: s + text[s..].trim_end().len() });
+    }
+    spans
+}
+
+/// Finds quoted dialogue, quotes included. Straight double quotes toggle and
+/// curly quotes pair up; an unclosed quote ends at the line break, which
+/// covers the multi-paragraph dialogue convention.
+pub fn dialogue(text: &str) -> Vec<Span> {
+    let mut spans = Vec::new();
+    let mut open: Option<usize> = None;
+
+    for (pos, c) in text.char_indices() {
+        match (c, open) {
+            ('"', None) | ('“', None) => open = Some(pos),
+            ('"', Some(s)) | ('”', Some(s)) => {
+                spans.push(Span { start: s, end: pos + c.len_utf8() });
+                open = None;
+            }
+            ('\n', Some(s)) => {
+                spans.push(Span { start: s, end: pos });
+                open = None;
+            }
+            _ => {}
+        }
+    }
+    if let Some(s) = open {
+        spans.push(Span { start: s, end: text.len() });
+    }
+    spans
+}
+
+/// Byte range of the quoted words inside a dialogue span, quotes excluded.
+pub fn dialogue_inner(text: &str, span: Span) -> Span {
+    let slice = &text[span.start..span.end];
+    let open = slice.chars().next().map_or(0, char::len_utf8);
+    let close = match slice.chars().next_back() {
+        Some(c @ ('"' | '”')) if slice.len() > open => c.len_utf8(),
+        _ => 0,
+    };
+    Span { start: span.start + open, end: span.end - close }
+}
+
+/// First alphabetic character in the range, or `None` if a digit comes first.
+pub fn first_letter(text: &str, span: Span) -> Option<(usize, char)> {
+    for (i, c) in text[span.start..span.end].char_indices() {
+        if c.is_alphabetic() {
+            return Some((span.start + i, c));
+        }
+        if c.is_ascii_digit() {
+            return None;
+        }
+    }
+    None
+}
+
+/// The word starting at `pos`, apostrophes included.
+pub fn word_at(text: &str, pos: usize) -> Span {
+    let end = text[pos..]
+        .find(|c: char| !(c.is_alphabetic() || c == '\'' || c == '’'))
+        .map_or(text.len(), |e| pos + e);
+    Span { start: pos, end }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sentence_texts(text: &str) -> Vec<&str> {
+        sentences(text).into_iter().map(|s| &text[s.start..s.end]).collect()
+    }
+
+    #[test]
+    fn sentence_spans() {
+        let cases: Vec<(&str, Vec<&str>)> = vec![
+            ("It was late. He left.", vec!["It was late.", "He left."]),
+            ("Mr. Vane waited.", vec!["Mr. Vane waited."]),
+            ("It was No. 5 on the list. He left.", vec!["It was No. 5 on the list.", "He left."]),
+            ("I said no. Then he left.", vec!["I said no.", "Then he left."]),
+            ("He waited... Then he left.", vec!["He waited...", "Then he left."]),
+            ("He waited... and waited.", vec!["He waited... and waited."]),
+            ("\"Where?\" she asked. He shrugged.", vec!["\"Where?\" she asked.", "He shrugged."]),
+            ("\"Stop!\" He ran.", vec!["\"Stop!\"", "He ran."]),
+            ("No end\nNew line", vec!["No end", "New line"]),
+            ("It cost 3.50 dollars.", vec!["It cost 3.50 dollars."]),
+        ];
+        for (text, expected) in cases {
+            assert_eq!(sentence_texts(text), expected, "{}", text);
+        }
+    }
+
+    #[test]
+    fn dialogue_spans() {
+        let cases: Vec<(&str, Vec<&str>)> = vec![
+            ("He said, \"Go.\" She went.", vec!["\"Go.\""]),
+            ("“Hi,” she said, “there.”", vec!["“Hi,”", "“there.”"]),
+            ("\"First paragraph\n\"Second.\"", vec!["\"First paragraph", "\"Second.\""]),
+            ("\"Unclosed", vec!["\"Unclosed"]),
+        ];
+        for (text, expected) in cases {
+            let found: Vec<&str> = dialogue(text).into_iter().map(|d| &text[d.start..d.end]).collect();
+            assert_eq!(found, expected, "{}", text);
+        }
+        let text = "“Hi,” she said.";
+        let inner = dialogue_inner(text, dialogue(text)[0]);
+        assert_eq!(&text[inner.start..inner.end], "Hi,");
+    }
+
+    #[test]
+    fn letters_and_words() {
+        let text = "  'tis 5 o'clock";
+        assert_eq!(first_letter(text, Span { start: 0, end: text.len() }), Some((3, 't')));
+        assert_eq!(first_letter(text, Span { start: 6, end: text.len() }), None);
+        let at = text.find("o'clock").unwrap();
+        assert_eq!(word_at(text, at), Span { start: at, end: text.len() });
+    }
+}
@@ -0,0 +1,158 @@
+use std::ops::Range;
+use crate::segment::{self, Span};
+use crate::spellcheck;
+
+// Works out who speaks each line of dialogue. A name in the dialogue tag
+// after the quote wins, then one in the sentence leading into it. A quote
+// without a tag takes the speaker of the other quotes in its paragraph, or
+// the one character its paragraph names, or else the turn after the two
+// speakers trading lines before it.
+
+#[derive(Debug, Clone, Copy)]
+pub struct Quote {
+    // Byte range of the quote, marks included.
+    pub span: Span,
+    // Index into the names given, when known.
+    pub speaker: Option<usize>,
+}
+
+fn line_of(text: &str, pos: usize) -> Range<usize> {
+    let start = text[..pos].rfind('\n').map_or(0, |p| p + 1);
+    let end = text[pos..].find('\n').map_or(text.len(), |p| pos + p);
+    start..end
+}
+
+fn is_terminal(c: char) -> bool {
+    matches!(c, '.' | '!' | '?')
+}
+
+pub fn attribute(text: &str, names: &[&str]) -> Vec<Quote> {
+    // Either part of a full name identifies the character.
+    let words: Vec<(&str, usize)> = names
+        .iter()
+        .enumerate()
+        .flat_map(|(i, name)| name.split_whitespace().filter(|w| w.chars().count() > 1).map(move |w| (w, i)))
+        .collect();
+    let named = |region: &str| -> Vec<usize> {
+        spellcheck::tokenize(region)
+            .iter()
+            .filter_map(|t| words.iter().find(|(w, _)| *w == t.text).map(|(_, i)| *i))
+            .collect()
+    };
+
+    let spans = segment::dialogue(text);
+    let mut quotes = Vec::with_capacity(spans.len());
+    for (k, span) in spans.iter().enumerate() {
+        let line = line_of(text, span.start);
+        let next = spans.get(k + 1).map_or(line.end, |s| s.start.min(line.end));
+        let prev = if k > 0 { spans[k - 1].end.max(line.start) } else { line.start };
+        let after = &text[span.end.min(next)..next];
+        let after = after.find(is_terminal).map_or(after, |p| &after[..p]);
+        let before = &text[prev..span.start];
+        let before = before.rfind(is_terminal).map_or(before, |p| &before[p + 1..]);
+        let speaker = named(after).first().or(named(before).last()).copied();
+        quotes.push(Quote { span: *span, speaker });
+    }
+
+    // Quotes grouped by paragraph.
+    let mut paragraphs: Vec<(Range<usize>, Range<usize>)> = Vec::new();
+    for (k, quote) in quotes.iter().enumerate() {
+        let line = line_of(text, quote.span.start);
+        match paragraphs.last_mut() {
+            Some((l, members)) if *l == line => members.end = k + 1,
+            _ => paragraphs.push((line, k..k + 1)),
+        }
+    }
+
+    let mut speakers: Vec<Option<usize>> = Vec::with_capacity(paragraphs.len());
+    for (line, members) in &paragraphs {
+        let mut tagged: Vec<usize> = quotes[members.clone()].iter().filter_map(|q| q.speaker).collect();
+        tagged.dedup();
+        let fill = match tagged.as_slice() {
+            [one] => Some(*one),
+            [] => {
+                let mut narration = Vec::new();
+                let mut at = line.start;
+                for quote in &quotes[members.clone()] {
+                    narration.extend(named(&text[at..quote.span.start]));
+                    at = quote.span.end;
+                }
+                narration.extend(named(&text[at.min(line.end)..line.end]));
+                narration.sort_unstable();
+                narration.dedup();
+                (narration.len() == 1).then(|| narration[0])
+            }
+            _ => None,
+        };
+        if let Some(speaker) = fill {
+            quotes[members.clone()].iter_mut().filter(|q| q.speaker.is_none()).for_each(|q| q.speaker = Some(speaker));
+        }
+        speakers.push(fill);
+    }
+
+    // Turn-taking: an untagged paragraph straight after lines from A then B
+    // goes back to A. Narration between paragraphs breaks the exchange.
+    for p in 2..paragraphs.len() {
+        let adjacent = |a: usize, b: usize| text[paragraphs[a].0.end..paragraphs[b].0.start].trim().is_empty();
+        let untagged = quotes[paragraphs[p].1.clone()].iter().all(|q| q.speaker.is_none());
+        if !untagged || !adjacent(p - 2, p - 1) || !adjacent(p - 1, p) {
+            continue;
+        }
+        if let (Some(a), Some(b)) = (speakers[p - 2], speakers[p - 1]) {
+            if a != b {
+                speakers[p] = Some(a);
+                quotes[paragraphs[p].1.clone()].iter_mut().for_each(|q| q.speaker = Some(a));
+            }
+        }
+    }
+    quotes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NAMES: &[&str] = &["Anna Vance", "Tom"];
+
+    // Each quote's words with its speaker's name, in text order.
+    fn speakers(text: &str) -> Vec<(&str, Option<&str>)> {
+        attribute(text, NAMES)
+            .into_iter()
+            .map(|q| {
+                let inner = segment::dialogue_inner(text, q.span);
+                (&text[inner.start..inner.end], q.speaker.map(|s| NAMES[s]))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn attribution() {
+        let cases = [
+            // Tag after the quote, by either part of the name.
+            ("\"Go home,\" Vance said.", vec![("Go home,", Some("Anna Vance"))]),
+            // Lead-in tag.
+            ("Tom shrugged. \"Fine.\"", vec![("Fine.", Some("Tom"))]),
+            // The tag after the quote wins over the lead-in.
+            ("Tom looked up. \"Now,\" Anna said.", vec![("Now,", Some("Anna Vance"))]),
+            // The one character an untagged paragraph names.
+            ("\"Later.\" The door closed behind Tom.", vec![("Later.", Some("Tom"))]),
+            // A tagged quote lends its speaker to the rest of its paragraph.
+            ("\"Wait,\" Anna said. \"Come back.\"", vec![("Wait,", Some("Anna Vance")), ("Come back.", Some("Anna Vance"))]),
+            // Two names and no tag: unknown.
+            ("Anna glared at Tom. \"Well?\"", vec![("Well?", None)]),
+            // A, B, then untagged lines alternate.
+            (
+                "\"Ready?\" Anna asked.\n\"Yes,\" Tom said.\n\"Then go.\"\n\"Going.\"",
+                vec![("Ready?", Some("Anna Vance")), ("Yes,", Some("Tom")), ("Then go.", Some("Anna Vance")), ("Going.", Some("Tom"))],
+            ),
+            // Narration between paragraphs breaks the exchange.
+            (
+                "\"Ready?\" Anna asked.\n\"Yes,\" Tom said.\nRain hammered the roof.\n\"Then go.\"",
+                vec![("Ready?", Some("Anna Vance")), ("Yes,", Some("Tom")), ("Then go.", None)],
+            ),
+        ];
+        for (text, expected) in cases {
+            assert_eq!(speakers(text), expected, "{}", text);
+        }
+    }
+}
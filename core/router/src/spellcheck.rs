@@ -1,93 +1,529 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
 use once_cell::sync::Lazy;
+use crate::{hunspell, phonetic, segment};
 
-static COMMON_WORDS: Lazy<HashSet<&'static str>> = Lazy::new(|| {
-    let words = include_str!("common_words.txt");
-    words.lines().map(|w| w.trim()).collect()
-});
+const MAX_EDIT_DISTANCE: usize = 2;
+// Only the first PREFIX_LENGTH chars are indexed, which keeps the delete
+// table small enough for full Hunspell dictionaries.
+const PREFIX_LENGTH: usize = 7;
+const BUILTIN_WORDS: &str = include_str!("common_words.txt");
 
-pub fn is_word_correct(word: &str) -> bool {
-    let word_lower = word.to_lowercase();
-    
-    if word_lower.is_empty() || word_lower.len() < 2 {
-        return true;
+// SymSpell-style index: every dictionary word is stored together with all of
+// its variants up to MAX_EDIT_DISTANCE deletions, so a lookup only has to
+// generate the deletions of the misspelling instead of scanning every entry.
+// A second index keyed by Double Metaphone codes catches words that sound
+// right but are spelled too far off for the edit-distance index. It can only
+// suggest words the dictionary has, which for the built-in list means the
+// commonest ones; "phenomenon" needs a loaded Hunspell dictionary.
+pub struct Dictionary {
+    words: Vec<String>,
+    codes: Vec<(String, String)>,
+    ranks: HashMap<String, usize>,
+    deletes: HashMap<String, Vec<usize>>,
+    phonetic: HashMap<String, Vec<usize>>,
+}
+
+impl Dictionary {
+    // Words are ranked by position, so the source list should be ordered from
+    // most to least frequent.
+    pub fn from_words<'a>(words: impl IntoIterator<Item = &'a str>) -> Self {
+        let mut dict = Dictionary {
+            words: Vec::new(),
+            codes: Vec::new(),
+            ranks: HashMap::new(),
+            deletes: HashMap::new(),
+            phonetic: HashMap::new(),
+        };
+        for word in words {
+            dict.insert(word);
+        }
+        dict
     }
-    
-    if COMMON_WORDS.contains(word_lower.as_str()) {
-        return true;
+
+    fn insert(&mut self, word: &str) {
+        let word = word.trim().to_lowercase();
+        if word.is_empty() || self.ranks.contains_key(&word) {
+            return;
+        }
+        let idx = self.words.len();
+        for variant in deletes(prefix(&word), MAX_EDIT_DISTANCE) {
+            self.deletes.entry(variant).or_default().push(idx);
+        }
+        let codes = phonetic::double_metaphone(&word);
+        for code in phonetic_keys(&codes) {
+            self.phonetic.entry(code.to_string()).or_default().push(idx);
+        }
+        self.codes.push(codes);
+        self.ranks.insert(word.clone(), idx);
+        self.words.push(word);
+    }
+
+    pub fn contains(&self, word: &str) -> bool {
+        self.ranks.contains_key(word)
+    }
+
+    pub fn len(&self) -> usize {
+        self.words.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.words.is_empty()
+    }
+
+    // Candidates within MAX_EDIT_DISTANCE plus sound-alike words, best score
+    // first, ties broken by frequency rank.
+    pub fn lookup(&self, word: &str, max_suggestions: usize) -> Vec<(String, f32)> {
+        let word = word.to_lowercase();
+        let codes = phonetic::double_metaphone(&word);
+        let mut seen = HashSet::new();
+        let mut candidates: Vec<(f32, usize)> = Vec::new();
+
+        for variant in deletes(prefix(&word), MAX_EDIT_DISTANCE) {
+            let Some(indices) = self.deletes.get(&variant) else { continue };
+            for &idx in indices {
+                if !seen.insert(idx) {
+                    continue;
+                }
+                let candidate = &self.words[idx];
+                if candidate.chars().count() < 2 || *candidate == word {
+                    continue;
+                }
+                if damerau_levenshtein(&word, candidate, MAX_EDIT_DISTANCE).is_some() {
+                    candidates.push((score(&word, &codes, candidate, &self.codes[idx]), idx));
+                }
+            }
+        }
+
+        let limit = phonetic_limit(&word);
+        for key in phonetic_keys(&codes) {
+            let Some(indices) = self.phonetic.get(key) else { continue };
+            for &idx in indices {
+                if !seen.insert(idx) || self.words[idx] == word {
+                    continue;
+                }
+                if weighted_distance(&word, &self.words[idx]) <= limit {
+                    candidates.push((score(&word, &codes, &self.words[idx], &self.codes[idx]), idx));
+                }
+            }
+        }
+
+        candidates.sort_by(|a, b| a.0.total_cmp(&b.0).then(a.1.cmp(&b.1)));
+        candidates.truncate(max_suggestions);
+        candidates.into_iter().map(|(s, idx)| (self.words[idx].clone(), s)).collect()
+    }
+}
+
+static DICTIONARY: Lazy<Dictionary> = Lazy::new(load_dictionary);
+
+// Hunspell dictionaries come from SPELLCHECK_DICT_DIR, optionally narrowed by
+// SPELLCHECK_LANGS (e.g. "en_GB,es_ES"). The built-in word list is used when
+// none load; otherwise it only lends its frequency order to the words the
+// loaded dictionaries share with it.
+fn load_dictionary() -> Dictionary {
+    let Some(dir) = std::env::var_os("SPELLCHECK_DICT_DIR").map(PathBuf::from) else {
+        return Dictionary::from_words(BUILTIN_WORDS.lines());
+    };
+    let langs: Vec<String> = std::env::var("SPELLCHECK_LANGS")
+        .map(|v| v.split(',').map(|l| l.trim().to_string()).filter(|l| !l.is_empty()).collect())
+        .unwrap_or_default();
+
+    let mut loaded = Vec::new();
+    for (dic, aff) in hunspell::find_dictionaries(&dir, &langs) {
+        match hunspell::load(&dic, &aff) {
+            Ok(words) => {
+                tracing::info!("loaded {} word forms from {}", words.len(), dic.display());
+                loaded.extend(words);
+            }
+            Err(e) => tracing::warn!("failed to load {}: {}", dic.display(), e),
+        }
+    }
+    if loaded.is_empty() {
+        tracing::warn!("no Hunspell dictionaries found in {}, using built-in word list", dir.display());
+        return Dictionary::from_words(BUILTIN_WORDS.lines());
     }
-    
-    if word_lower.chars().all(|c| c.is_uppercase() || !c.is_alphabetic()) {
+
+    let known: HashSet<String> = loaded.iter().map(|w| w.to_lowercase()).collect();
+    let frequent = BUILTIN_WORDS
+        .lines()
+        .filter(|w| known.contains(&w.trim().to_lowercase()));
+    Dictionary::from_words(frequent.chain(loaded.iter().map(String::as_str)))
+}
+
+// Builds the suggestion index up front so the first request does not pay for it.
+pub fn init() {
+    let dict = Lazy::force(&DICTIONARY);
+    tracing::info!("spellcheck dictionary loaded: {} words", dict.len());
+}
+
+fn prefix(word: &str) -> &str {
+    match word.char_indices().nth(PREFIX_LENGTH) {
+        Some((i, _)) => &word[..i],
+        None => word,
+    }
+}
+
+// Single-letter codes match too much of the dictionary to be useful.
+fn phonetic_keys(codes: &(String, String)) -> Vec<&str> {
+    let mut keys = Vec::new();
+    for code in [&codes.0, &codes.1] {
+        if code.len() >= 2 && !keys.contains(&code.as_str()) {
+            keys.push(code.as_str());
+        }
+    }
+    keys
+}
+
+// Sound-alike candidates may be further off than MAX_EDIT_DISTANCE, but not
+// by more than about half the word.
+fn phonetic_limit(word: &str) -> f32 {
+    (word.chars().count() / 2 + 1) as f32
+}
+
+const SOUND_ALIKE_BONUS: f32 = 0.5;
+
+fn score(word: &str, codes: &(String, String), candidate: &str, candidate_codes: &(String, String)) -> f32 {
+    let sounds_alike = phonetic_keys(codes).iter().any(|k| phonetic_keys(candidate_codes).contains(k));
+    let bonus = if sounds_alike { SOUND_ALIKE_BONUS } else { 0.0 };
+    weighted_distance(word, candidate) - bonus
+}
+
+// Edit distance where the typing mistakes people actually make are cheap:
+// swapped neighbours, a neighbouring key, or a doubled or undoubled letter
+// cost half an edit.
+fn weighted_distance(s1: &str, s2: &str) -> f32 {
+    const CHEAP: f32 = 0.5;
+    let a: Vec<char> = s1.chars().collect();
+    let b: Vec<char> = s2.chars().collect();
+    let mut d = vec![vec![0.0f32; b.len() + 1]; a.len() + 1];
+
+    for i in 1..=a.len() {
+        d[i][0] = d[i - 1][0] + if i > 1 && a[i - 1] == a[i - 2] { CHEAP } else { 1.0 };
+    }
+    for j in 1..=b.len() {
+        d[0][j] = d[0][j - 1] + if j > 1 && b[j - 1] == b[j - 2] { CHEAP } else { 1.0 };
+    }
+
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let delete = if i > 1 && a[i - 1] == a[i - 2] { CHEAP } else { 1.0 };
+            let insert = if j > 1 && b[j - 1] == b[j - 2] { CHEAP } else { 1.0 };
+            let substitute = if a[i - 1] == b[j - 1] {
+                0.0
+            } else if phonetic::keys_adjacent(a[i - 1], b[j - 1]) {
+                CHEAP
+            } else {
+                1.0
+            };
+            let mut best = (d[i - 1][j] + delete).min(d[i][j - 1] + insert).min(d[i - 1][j - 1] + substitute);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                best = best.min(d[i - 2][j - 2] + CHEAP);
+            }
+            d[i][j] = best;
+        }
+    }
+    d[a.len()][b.len()]
+}
+
+// The word itself plus every string reachable by deleting up to `max` chars.
+fn deletes(word: &str, max: usize) -> HashSet<String> {
+    let mut out = HashSet::new();
+    out.insert(word.to_string());
+    let mut frontier = vec![word.to_string()];
+
+    for _ in 0..max {
+        let mut next = Vec::new();
+        for w in &frontier {
+            let chars: Vec<char> = w.chars().collect();
+            if chars.len() <= 1 {
+                continue;
+            }
+            for i in 0..chars.len() {
+                let variant: String = chars[..i].iter().chain(&chars[i + 1..]).collect();
+                if out.insert(variant.clone()) {
+                    next.push(variant);
+                }
+            }
+        }
+        frontier = next;
+    }
+    out
+}
+
+// Optimal string alignment distance over chars, abandoned once it is certain
+// to exceed `max`.
+fn damerau_levenshtein(s1: &str, s2: &str, max: usize) -> Option<usize> {
+    let a: Vec<char> = s1.chars().collect();
+    let b: Vec<char> = s2.chars().collect();
+    if a.len().abs_diff(b.len()) > max {
+        return None;
+    }
+
+    let mut prev_prev = vec![0; b.len() + 1];
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    let mut cur = vec![0; b.len() + 1];
+
+    for i in 1..=a.len() {
+        cur[0] = i;
+        let mut row_min = cur[0];
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            cur[j] = (prev[j] + 1).min(cur[j - 1] + 1).min(prev[j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                cur[j] = cur[j].min(prev_prev[j - 2] + 1);
+            }
+            row_min = row_min.min(cur[j]);
+        }
+        if row_min > max {
+            return None;
+        }
+        std::mem::swap(&mut prev_prev, &mut prev);
+        std::mem::swap(&mut prev, &mut cur);
+    }
+
+    let distance = prev[b.len()];
+    (distance <= max).then_some(distance)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Case {
+    Lower,
+    Capitalized,
+    AllCaps,
+    Other,
+}
+
+fn case_of(word: &str) -> Case {
+    let letters: Vec<char> = word.chars().filter(|c| c.is_alphabetic()).collect();
+    match letters.as_slice() {
+        [] => Case::Other,
+        [first, ..] if first.is_lowercase() && letters.iter().all(|c| c.is_lowercase()) => Case::Lower,
+        [_, _, ..] if letters.iter().all(|c| c.is_uppercase()) => Case::AllCaps,
+        [first, ..] if first.is_uppercase() => Case::Capitalized,
+        _ => Case::Other,
+    }
+}
+
+// Checks a word out of context: acronyms and capitalized words are assumed
+// to be names.
+pub fn is_word_correct(word: &str) -> bool {
+    if word.chars().filter(|c| c.is_alphabetic()).count() < 2 {
         return true;
     }
-    
-    if word_lower.chars().next().unwrap().is_uppercase() && word.len() > 1 {
+
+    if DICTIONARY.contains(word.to_lowercase().as_str()) {
         return true;
     }
-    
-    false
+
+    matches!(case_of(word), Case::AllCaps | Case::Capitalized)
+}
+
+pub fn is_word_known(word: &str, user_words: &HashSet<String>) -> bool {
+    is_word_correct(word) || user_words.contains(&word.to_lowercase())
 }
 
-fn levenshtein_distance(s1: &str, s2: &str) -> usize {
-    let len1 = s1.len();
-    let len2 = s2.len();
-    let mut matrix = vec![vec![0; len2 + 1]; len1 + 1];
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Token<'a> {
+    pub start: usize,
+    pub end: usize,
+    pub text: &'a str,
+    pub sentence_initial: bool,
+}
 
-    for i in 0..=len1 {
-        matrix[i][0] = i;
-    }
-    for j in 0..=len2 {
-        matrix[0][j] = j;
+fn is_apostrophe(c: char) -> bool {
+    c == '\'' || c == '’'
+}
+
+// Words are runs of letters joined by single apostrophes or hyphens, so
+// "don't" and "well-known" stay whole while quotes and dashes do not.
+// Sentence-initial also covers the first word inside a quotation.
+pub fn tokenize(text: &str) -> Vec<Token<'_>> {
+    let mut initial: HashSet<usize> = segment::sentences(text)
+        .into_iter()
+        .filter_map(|s| segment::first_letter(text, s).map(|(pos, _)| pos))
+        .collect();
+    initial.extend(
+        segment::dialogue(text)
+            .into_iter()
+            .filter_map(|d| segment::first_letter(text, segment::dialogue_inner(text, d)).map(|(pos, _)| pos)),
+    );
+
+    let chars: Vec<(usize, char)> = text.char_indices().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        if !chars[i].1.is_alphabetic() {
+            i += 1;
+            continue;
+        }
+        let start = chars[i].0;
+        let mut j = i + 1;
+        while j < chars.len() {
+            let c = chars[j].1;
+            let joins = (is_apostrophe(c) || c == '-')
+                && chars.get(j + 1).is_some_and(|&(_, n)| n.is_alphabetic());
+            if c.is_alphabetic() {
+                j += 1;
+            } else if joins {
+                j += 2;
+            } else {
+                break;
+            }
+        }
+        let end = chars.get(j).map_or(text.len(), |&(p, _)| p);
+        tokens.push(Token { start, end, text: &text[start..end], sentence_initial: initial.contains(&start) });
+        i = j;
     }
+    tokens
+}
 
-    for (i, c1) in s1.chars().enumerate() {
-        for (j, c2) in s2.chars().enumerate() {
-            let cost = if c1 == c2 { 0 } else { 1 };
-            matrix[i + 1][j + 1] = (matrix[i][j + 1] + 1)
-                .min(matrix[i + 1][j] + 1)
-                .min(matrix[i][j] + cost);
+// Possessive and contraction endings, checked against the stem instead.
+const CLITICS: &[&str] = &["'s", "n't", "'re", "'ve", "'ll", "'d", "'m"];
+const IRREGULAR_CONTRACTIONS: &[&str] = &["can't", "won't", "shan't", "ain't"];
+
+fn strip_clitic(word: &str) -> &str {
+    let normalized = word.to_lowercase().replace('’', "'");
+    if IRREGULAR_CONTRACTIONS.contains(&normalized.as_str()) {
+        return "";
+    }
+    for clitic in CLITICS {
+        if normalized.ends_with(clitic) && normalized.len() > clitic.len() {
+            let cut = word.char_indices().rev().nth(clitic.chars().count() - 1).map_or(0, |(i, _)| i);
+            return &word[..cut];
         }
     }
+    word
+}
+
+fn needs_check(part: &str, sentence_initial: bool, user_words: &HashSet<String>, names: &HashSet<String>) -> bool {
+    if part.chars().count() < 3 {
+        return false;
+    }
+    let lower = part.to_lowercase();
+    if DICTIONARY.contains(lower.as_str()) || user_words.contains(&lower) {
+        return false;
+    }
+    match case_of(part) {
+        Case::AllCaps => false,
+        Case::Capitalized => sentence_initial && !names.contains(&lower),
+        Case::Lower | Case::Other => true,
+    }
+}
 
-    matrix[len1][len2]
+fn match_case(suggestion: String, original: &str) -> String {
+    match case_of(original) {
+        Case::Capitalized => {
+            let mut chars = suggestion.chars();
+            chars.next().map_or(String::new(), |f| f.to_uppercase().chain(chars).collect())
+        }
+        _ => suggestion,
+    }
 }
 
 pub fn suggest_corrections(word: &str, max_suggestions: usize) -> Vec<String> {
-    let word_lower = word.to_lowercase();
-    let mut candidates: Vec<(&str, usize)> = Vec::new();
-    
-    for &dict_word in COMMON_WORDS.iter() {
-        if dict_word.len() > 1 && (dict_word.len() as i32 - word_lower.len() as i32).abs() <= 2 {
-            let distance = levenshtein_distance(&word_lower, dict_word);
-            if distance <= 2 {
-                candidates.push((dict_word, distance));
-            }
+    DICTIONARY
+        .lookup(word, max_suggestions)
+        .into_iter()
+        .map(|(w, _)| w)
+        .collect()
+}
+
+// Project words are few enough to compare directly; they rank ahead of
+// dictionary words at the same distance.
+fn suggest_with_user_words(word: &str, max_suggestions: usize, user_words: &HashSet<String>) -> Vec<String> {
+    let lower = word.to_lowercase();
+    let codes = phonetic::double_metaphone(&lower);
+    let limit = phonetic_limit(&lower);
+    let mut merged: Vec<(f32, String)> = user_words
+        .iter()
+        .filter(|w| **w != lower)
+        .filter_map(|w| {
+            let w_codes = phonetic::double_metaphone(w);
+            let close = damerau_levenshtein(&lower, w, MAX_EDIT_DISTANCE).is_some()
+                || weighted_distance(&lower, w) <= limit
+                    && phonetic_keys(&codes).iter().any(|k| phonetic_keys(&w_codes).contains(k));
+            close.then(|| (score(&lower, &codes, w, &w_codes), w.clone()))
+        })
+        .collect();
+
+    for (w, s) in DICTIONARY.lookup(word, max_suggestions) {
+        if !merged.iter().any(|(_, m)| *m == w) {
+            merged.push((s, w));
         }
     }
-    
-    candidates.sort_by_key(|&(_, dist)| dist);
-    candidates.truncate(max_suggestions);
-    candidates.into_iter().map(|(w, _)| w.to_string()).collect()
+    merged.sort_by(|a, b| a.0.total_cmp(&b.0));
+    merged.truncate(max_suggestions);
+    merged.into_iter().map(|(_, w)| w).collect()
 }
 
-pub fn check_spelling(text: &str) -> Vec<(usize, usize, String, Vec<String>)> {
+pub fn check_spelling(text: &str, user_words: &HashSet<String>) -> Vec<(usize, usize, String, Vec<String>)> {
     let mut errors = Vec::new();
-    let mut current_pos = 0;
-    
-    for word_match in text.split(|c: char| !c.is_alphabetic() && c != '\'' && c != '-') {
-        if !word_match.is_empty() {
-            let word = word_match.trim_matches(|c: char| !c.is_alphabetic());
-            
-            if !word.is_empty() && word.len() >= 3 && !is_word_correct(word) {
-                let pos = text[current_pos..].find(word).map(|p| current_pos + p);
-                if let Some(start) = pos {
-                    let suggestions = suggest_corrections(word, 3);
-                    errors.push((start, start + word.len(), word.to_string(), suggestions));
-                }
+    let tokens = tokenize(text);
+    // A capitalized word seen mid-sentence is a name wherever it appears.
+    let names: HashSet<String> = tokens
+        .iter()
+        .filter(|t| !t.sentence_initial && case_of(t.text) == Case::Capitalized)
+        .map(|t| strip_clitic(t.text).to_lowercase())
+        .collect();
+
+    for token in tokens {
+        let stem = strip_clitic(token.text);
+        let mut offset = 0;
+        for (n, part) in stem.split('-').enumerate() {
+            let start = token.start + offset;
+            offset += part.len() + 1;
+            if !needs_check(part, token.sentence_initial && n == 0, user_words, &names) {
+                continue;
             }
+            let suggestions = suggest_with_user_words(part, 3, user_words)
+                .into_iter()
+                .map(|s| match_case(s, part))
+                .collect();
+            errors.push((start, start + part.len(), part.to_string(), suggestions));
         }
-        current_pos += word_match.len() + 1;
     }
-    
+
     errors
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spans(text: &str) -> Vec<(&str, usize, usize)> {
+        tokenize(text).into_iter().map(|t| (t.text, t.start, t.end)).collect()
+    }
+
+    #[test]
+    fn token_spans() {
+        let cases = [
+            ("Anna's cat", vec![("Anna's", 0, 6), ("cat", 7, 10)]),
+            ("James’s hat", vec![("James’s", 0, 9), ("hat", 10, 13)]),
+            ("a well-known man", vec![("a", 0, 1), ("well-known", 2, 12), ("man", 13, 16)]),
+            ("ice—cold", vec![("ice", 0, 3), ("cold", 6, 10)]),
+            ("naïve café", vec![("naïve", 0, 6), ("café", 7, 12)]),
+            ("end- 'quoted'", vec![("end", 0, 3), ("quoted", 6, 12)]),
+        ];
+        for (text, expected) in cases {
+            assert_eq!(spans(text), expected, "{}", text);
+        }
+    }
+
+    #[test]
+    fn phonetic_suggestions() {
+        let dictionary = Dictionary::from_words(["phenomenon", "phenomena", "psychology", "physiology", "sociology", "nominee"]);
+        let cases = [("fenomenon", "phenomenon"), ("sycology", "psychology")];
+        for (misspelled, expected) in cases {
+            let suggestions = dictionary.lookup(misspelled, 3);
+            assert_eq!(suggestions.first().map(|(w, _)| w.as_str()), Some(expected), "{}: {:?}", misspelled, suggestions);
+        }
+    }
+
+    #[test]
+    fn clitics() {
+        let cases = [("Anna's", "Anna"), ("James’s", "James"), ("don't", "do"), ("they're", "they"), ("can't", ""), ("Won’t", ""), ("'s", "'s"), ("cats", "cats")];
+        for (word, stem) in cases {
+            assert_eq!(strip_clitic(word), stem, "{}", word);
+        }
+    }
+}
@@ -0,0 +1,40 @@
+use std::path::Path;
+use std::sync::Arc;
+use crate::chat_sessions::ChatSessions;
+use crate::http_client::HttpClient;
+use crate::jobs::JobRegistry;
+use crate::provider::{self, LlmProvider};
+use crate::rebase::BaseTexts;
+use crate::scheduler::Scheduler;
+use crate::storage;
+use crate::tiers::Tiers;
+use crate::user_dict::UserDictionaries;
+
+#[derive(Clone)]
+pub struct AppState {
+    pub user_dicts: Arc<UserDictionaries>,
+    pub jobs: Arc<JobRegistry>,
+    pub chat_sessions: Arc<ChatSessions>,
+    pub llm: Arc<dyn LlmProvider>,
+    pub tiers: Arc<Tiers>,
+    pub scheduler: Arc<Scheduler>,
+    pub bases: Arc<BaseTexts>,
+}
+
+impl AppState {
+    pub fn from_env() -> Self {
+        AppState::new(&storage::data_dir(), provider::from_env(HttpClient::from_env()), Tiers::from_env())
+    }
+
+    pub fn new(data_dir: &Path, llm: Arc<dyn LlmProvider>, tiers: Tiers) -> Self {
+        AppState {
+            user_dicts: Arc::new(UserDictionaries::new(data_dir.join("dictionaries"))),
+            jobs: Arc::new(JobRegistry::default()),
+            chat_sessions: Arc::new(ChatSessions::new(data_dir.join("chats"))),
+            llm,
+            tiers: Arc::new(tiers),
+            scheduler: Arc::new(Scheduler::default()),
+            bases: Arc::new(BaseTexts::default()),
+        }
+    }
+}
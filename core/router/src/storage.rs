@@ -0,0 +1,21 @@
+use std::path::{Path, PathBuf};
+
+// Root for everything the router persists between runs (user dictionaries,
+// chat sessions). Defaults to ./data next to the router.
+pub fn data_dir() -> PathBuf {
+    std::env::var_os("ROUTER_DATA_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from("data"))
+}
+
+// Project and session ids become file names, so only a conservative
+// character set is accepted.
+pub fn valid_id(id: &str) -> bool {
+    !id.is_empty()
+        && id.len() <= 64
+        && id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+pub fn project_file(dir: &Path, project: &str, ext: &str) -> PathBuf {
+    dir.join(format!("{}.{}", project, ext))
+}
@@ -0,0 +1,96 @@
+use axum::response::sse::{Event, KeepAlive, Sse};
+use futures_util::{pin_mut, Stream, StreamExt};
+use serde::Serialize;
+use std::convert::Infallible;
+use std::future::Future;
+use crate::error::RouterError;
+use crate::jobs::{Cancelled, JobGuard};
+use crate::provider::LlmError;
+
+#[derive(Serialize)]
+struct TokenEvent<'a> {
+    text: &'a str,
+}
+
+fn json_event<T: Serialize>(name: &str, data: &T) -> Event {
+    Event::default()
+        .event(name)
+        .json_data(data)
+        .unwrap_or_else(|e| Event::default().event("error").data(e.to_string()))
+}
+
+// Same `{code, message, retryable}` body as a failed non-streaming request.
+pub fn error_event(err: &RouterError) -> Event {
+    json_event("error", &err.body())
+}
+
+#[derive(Serialize)]
+struct JobEvent<'a> {
+    request_id: &'a str,
+}
+
+// Relays a generation as Server-Sent Events: a `job` event with the request
+// id (for /api/jobs/{id}/cancel), one `token` event per fragment, then
+// whatever `finish` builds from the full text (e.g. a `done` event). Failures,
+// a blank reply included, end the stream with an `error` event and skip
+// `finish`; cancellation ends it with `cancelled`. If the client disconnects
+// the stream is dropped, and the upstream request with it.
+pub fn sse_generation<U, S, F>(job: JobGuard, upstream: U, finish: F) -> Sse<impl Stream<Item = Result<Event, Infallible>>>
+where
+    U: Future<Output = Result<S, LlmError>> + Send + 'static,
+    S: Stream<Item = Result<String, LlmError>> + Send + 'static,
+    F: FnOnce(String) -> Vec<Event> + Send + 'static,
+{
+    let stream = async_stream::stream! {
+        yield Ok(json_event("job", &JobEvent { request_id: &job.id }));
+
+        let tokens = match job.run(upstream).await {
+            Ok(Ok(tokens)) => tokens,
+            Ok(Err(e)) => {
+                yield Ok(error_event(&RouterError::from(e)));
+                return;
+            }
+            Err(Cancelled) => {
+                yield Ok(cancelled_event(&job.id));
+                return;
+            }
+        };
+        pin_mut!(tokens);
+
+        let mut full = String::new();
+        loop {
+            match job.run(tokens.next()).await {
+                Ok(Some(Ok(text))) => {
+                    full.push_str(&text);
+                    yield Ok(json_event("token", &TokenEvent { text: &text }));
+                }
+                Ok(Some(Err(e))) => {
+                    yield Ok(error_event(&RouterError::from(e)));
+                    return;
+                }
+                Ok(None) => break,
+                Err(Cancelled) => {
+                    yield Ok(cancelled_event(&job.id));
+                    return;
+                }
+            }
+        }
+        job.finish();
+        if full.trim().is_empty() {
+            yield Ok(error_event(&RouterError::from(LlmError::Empty)));
+            return;
+        }
+        for event in finish(full) {
+            yield Ok(event);
+        }
+    };
+    Sse::new(stream).keep_alive(KeepAlive::default())
+}
+
+fn cancelled_event(request_id: &str) -> Event {
+    json_event("cancelled", &JobEvent { request_id })
+}
+
+pub fn done_event<T: Serialize>(data: &T) -> Event {
+    json_event("done", data)
+}
@@ -0,0 +1,201 @@
+use axum::{extract::State, Json};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use crate::error::RouterError;
+use crate::{storage, AppState};
+
+// Sampling options sent to the backend. Unset fields are left to the
+// backend's own defaults; field names follow Ollama's `options` object.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct GenOptions {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub top_p: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub num_ctx: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub num_predict: Option<i32>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub stop: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seed: Option<i64>,
+}
+
+impl GenOptions {
+    // `self` with every field set in `overrides` replaced.
+    pub fn merged(&self, overrides: &GenOptions) -> GenOptions {
+        GenOptions {
+            temperature: overrides.temperature.or(self.temperature),
+            top_p: overrides.top_p.or(self.top_p),
+            num_ctx: overrides.num_ctx.or(self.num_ctx),
+            num_predict: overrides.num_predict.or(self.num_predict),
+            stop: if overrides.stop.is_empty() { self.stop.clone() } else { overrides.stop.clone() },
+            seed: overrides.seed.or(self.seed),
+        }
+    }
+
+    // Per-request overrides must stay within what every backend accepts.
+    // num_predict -1 means "until the model stops".
+    pub fn check_bounds(&self) -> Result<(), RouterError> {
+        let bad = |msg: String| Err(RouterError::BadInput(msg));
+        if let Some(t) = self.temperature.filter(|t| !(0.0..=2.0).contains(t)) {
+            return bad(format!("temperature {} is outside 0.0..=2.0", t));
+        }
+        if let Some(p) = self.top_p.filter(|p| !(*p > 0.0 && *p <= 1.0)) {
+            return bad(format!("top_p {} is outside 0.0 (exclusive) to 1.0", p));
+        }
+        if let Some(n) = self.num_ctx.filter(|n| !(MIN_CTX..=MAX_CTX).contains(n)) {
+            return bad(format!("num_ctx {} is outside {}..={}", n, MIN_CTX, MAX_CTX));
+        }
+        if let Some(n) = self.num_predict.filter(|n| *n == 0 || !(-1..=MAX_PREDICT).contains(n)) {
+            return bad(format!("num_predict {} must be -1 or 1..={}", n, MAX_PREDICT));
+        }
+        if self.stop.len() > MAX_STOP || self.stop.iter().any(|s| s.is_empty() || s.chars().count() > 32) {
+            return bad(format!("stop takes at most {} non-empty sequences of up to 32 characters", MAX_STOP));
+        }
+        Ok(())
+    }
+}
+
+const MIN_CTX: u32 = 512;
+const MAX_CTX: u32 = 131_072;
+const MAX_PREDICT: i32 = 32_768;
+const MAX_STOP: usize = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Tier {
+    Small,
+    Medium,
+    Large,
+}
+
+impl Tier {
+    pub const ALL: [Tier; 3] = [Tier::Small, Tier::Medium, Tier::Large];
+}
+
+// Ollama treats "llama3.2" and "llama3.2:latest" as the same model.
+pub fn same_model(a: &str, b: &str) -> bool {
+    let full = |m: &str| if m.contains(':') { m.to_string() } else { format!("{}:latest", m) };
+    full(a) == full(b)
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TierConfig {
+    pub model: String,
+    #[serde(default)]
+    pub options: GenOptions,
+}
+
+// The small/medium/large models the desktop app picks between, with the
+// sampling defaults for each. Read from ROUTER_TIERS, or tiers.json in the
+// data directory, falling back to the same models the app ships with.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Tiers {
+    #[serde(default = "default_small")]
+    pub small: TierConfig,
+    #[serde(default = "default_medium")]
+    pub medium: TierConfig,
+    #[serde(default = "default_large")]
+    pub large: TierConfig,
+}
+
+fn tier(model: &str, temperature: f32, top_p: f32, num_ctx: u32, num_predict: i32) -> TierConfig {
+    TierConfig {
+        model: model.to_string(),
+        options: GenOptions {
+            temperature: Some(temperature),
+            top_p: Some(top_p),
+            num_ctx: Some(num_ctx),
+            num_predict: Some(num_predict),
+            ..GenOptions::default()
+        },
+    }
+}
+
+fn default_small() -> TierConfig {
+    tier("llama3.2:3b", 0.3, 0.9, 4096, 1024)
+}
+
+fn default_medium() -> TierConfig {
+    tier("llama3.2:latest", 0.5, 0.9, 8192, 2048)
+}
+
+fn default_large() -> TierConfig {
+    tier("llama3:70b", 0.7, 0.95, 8192, 4096)
+}
+
+impl Default for Tiers {
+    fn default() -> Self {
+        Tiers { small: default_small(), medium: default_medium(), large: default_large() }
+    }
+}
+
+impl Tiers {
+    pub fn from_env() -> Self {
+        let path = std::env::var("ROUTER_TIERS")
+            .map(Into::into)
+            .unwrap_or_else(|_| storage::data_dir().join("tiers.json"));
+        if !path.exists() {
+            return Tiers::default();
+        }
+        match Tiers::load(&path) {
+            Ok(tiers) => {
+                tracing::info!("model tiers loaded from {}", path.display());
+                tiers
+            }
+            Err(e) => {
+                tracing::warn!("ignoring {}: {}", path.display(), e);
+                Tiers::default()
+            }
+        }
+    }
+
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+        serde_json::from_str(&text).map_err(|e| e.to_string())
+    }
+
+    pub fn get(&self, tier: Tier) -> &TierConfig {
+        match tier {
+            Tier::Small => &self.small,
+            Tier::Medium => &self.medium,
+            Tier::Large => &self.large,
+        }
+    }
+
+    // The tier configured with this model, if any.
+    pub fn tier_of(&self, model: &str) -> Option<Tier> {
+        Tier::ALL.into_iter().find(|t| same_model(&self.get(*t).model, model))
+    }
+
+    // The tier's options with the request's overrides applied.
+    pub fn options(&self, tier: Tier, overrides: Option<&GenOptions>) -> Result<GenOptions, RouterError> {
+        let defaults = &self.get(tier).options;
+        match overrides {
+            Some(overrides) => {
+                overrides.check_bounds()?;
+                Ok(defaults.merged(overrides))
+            }
+            None => Ok(defaults.clone()),
+        }
+    }
+
+    // The model and options for a request that names its model or tier. An
+    // explicit tier wins; otherwise the tier configured with the requested
+    // model, then medium. The request's model, when given, replaces the
+    // tier's.
+    pub fn resolve(&self, tier: Option<Tier>, model: &str, overrides: Option<&GenOptions>) -> Result<(Tier, String, GenOptions), RouterError> {
+        let model = model.trim();
+        let tier = tier.or_else(|| self.tier_of(model)).unwrap_or(Tier::Medium);
+        let options = self.options(tier, overrides)?;
+        let model = if model.is_empty() { self.get(tier).model.clone() } else { model.to_string() };
+        Ok((tier, model, options))
+    }
+}
+
+pub async fn list(State(state): State<AppState>) -> Json<Tiers> {
+    Json(state.tiers.as_ref().clone())
+}
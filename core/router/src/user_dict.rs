@@ -0,0 +1,201 @@
+use axum::{extract::{Path, State}, Json};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::sync::RwLock;
+use crate::error::{ApiJson, RouterError};
+use crate::{spellcheck, storage, AppState};
+
+// Words an author has marked as correct for one project, persisted as one
+// lowercase word per line in <data_dir>/dictionaries/<project>.txt.
+pub struct UserDictionaries {
+    dir: PathBuf,
+    projects: RwLock<HashMap<String, HashSet<String>>>,
+}
+
+impl UserDictionaries {
+    pub fn new(dir: PathBuf) -> Self {
+        UserDictionaries { dir, projects: RwLock::new(HashMap::new()) }
+    }
+
+    fn load(&self, project: &str) -> HashSet<String> {
+        let path = storage::project_file(&self.dir, project, "txt");
+        std::fs::read_to_string(path)
+            .map(|s| s.lines().map(|w| w.trim().to_string()).filter(|w| !w.is_empty()).collect())
+            .unwrap_or_default()
+    }
+
+    fn save(&self, project: &str, words: &HashSet<String>) -> std::io::Result<()> {
+        std::fs::create_dir_all(&self.dir)?;
+        let mut sorted: Vec<&String> = words.iter().collect();
+        sorted.sort();
+        let body: String = sorted.into_iter().map(|w| format!("{}\n", w)).collect();
+        std::fs::write(storage::project_file(&self.dir, project, "txt"), body)
+    }
+
+    pub fn words(&self, project: &str) -> HashSet<String> {
+        if let Some(words) = self.projects.read().unwrap().get(project) {
+            return words.clone();
+        }
+        let words = self.load(project);
+        self.projects.write().unwrap().insert(project.to_string(), words.clone());
+        words
+    }
+
+    // The words of `project`, or none when there is no valid project.
+    pub fn for_project(&self, project: Option<&str>) -> HashSet<String> {
+        match project {
+            Some(project) if storage::valid_id(project) => self.words(project),
+            _ => HashSet::new(),
+        }
+    }
+
+    // Holds the write lock from read to save, so concurrent changes to a
+    // project never start from the same old set and drop each other's words.
+    fn update(&self, project: &str, f: impl FnOnce(&mut HashSet<String>)) -> std::io::Result<HashSet<String>> {
+        let mut projects = self.projects.write().unwrap();
+        let mut words = match projects.get(project) {
+            Some(words) => words.clone(),
+            None => self.load(project),
+        };
+        f(&mut words);
+        self.save(project, &words)?;
+        projects.insert(project.to_string(), words.clone());
+        Ok(words)
+    }
+
+    pub fn add<'a>(&self, project: &str, new_words: impl IntoIterator<Item = &'a str>) -> std::io::Result<HashSet<String>> {
+        self.update(project, |words| {
+            words.extend(new_words.into_iter().map(normalize).filter(|w| !w.is_empty()));
+        })
+    }
+
+    pub fn remove(&self, project: &str, word: &str) -> std::io::Result<HashSet<String>> {
+        self.update(project, |words| {
+            words.remove(&normalize(word));
+        })
+    }
+}
+
+fn normalize(word: &str) -> String {
+    word.trim().trim_matches(|c: char| !c.is_alphanumeric()).to_lowercase()
+}
+
+fn sorted(words: HashSet<String>) -> Vec<String> {
+    let mut list: Vec<String> = words.into_iter().collect();
+    list.sort();
+    list
+}
+
+// Character names are taken whole (each part of "Eldrath Vane"); from
+// worldbuilding notes only the words the spellchecker does not know are kept.
+fn seed_terms(characters: &[String], worldbuilding: &str) -> Vec<String> {
+    let word_re = Regex::new(r"[\p{L}][\p{L}'’-]*[\p{L}]").unwrap();
+    let mut terms: Vec<String> = characters
+        .iter()
+        .flat_map(|name| word_re.find_iter(name).map(|m| m.as_str().to_string()).collect::<Vec<_>>())
+        .collect();
+    terms.extend(
+        word_re
+            .find_iter(worldbuilding)
+            .map(|m| m.as_str())
+            .filter(|w| w.chars().count() >= 3)
+            .flat_map(|w| w.split('-'))
+            .filter(|w| !spellcheck::is_word_correct(&w.to_lowercase()))
+            .map(String::from),
+    );
+    terms
+}
+
+fn check_project(project: &str) -> Result<(), RouterError> {
+    if storage::valid_id(project) {
+        Ok(())
+    } else {
+        Err(RouterError::BadInput(format!("invalid project id '{}'", project)))
+    }
+}
+
+fn io_error(e: std::io::Error) -> RouterError {
+    RouterError::Storage(format!("failed to save dictionary: {}", e))
+}
+
+#[derive(Serialize)]
+pub struct DictResp {
+    pub project: String,
+    pub words: Vec<String>,
+}
+
+#[derive(Deserialize)]
+pub struct AddWordsReq {
+    pub words: Vec<String>,
+}
+
+#[derive(Deserialize)]
+pub struct SeedReq {
+    #[serde(default)]
+    pub characters: Vec<String>,
+    #[serde(default)]
+    pub worldbuilding: String,
+}
+
+pub async fn list_words(State(state): State<AppState>, Path(project): Path<String>) -> Result<Json<DictResp>, RouterError> {
+    check_project(&project)?;
+    let words = sorted(state.user_dicts.words(&project));
+    Ok(Json(DictResp { project, words }))
+}
+
+pub async fn add_words(
+    State(state): State<AppState>,
+    Path(project): Path<String>,
+    ApiJson(req): ApiJson<AddWordsReq>,
+) -> Result<Json<DictResp>, RouterError> {
+    check_project(&project)?;
+    let words = state.user_dicts.add(&project, req.words.iter().map(String::as_str)).map_err(io_error)?;
+    Ok(Json(DictResp { project, words: sorted(words) }))
+}
+
+pub async fn remove_word(
+    State(state): State<AppState>,
+    Path((project, word)): Path<(String, String)>,
+) -> Result<Json<DictResp>, RouterError> {
+    check_project(&project)?;
+    let words = state.user_dicts.remove(&project, &word).map_err(io_error)?;
+    Ok(Json(DictResp { project, words: sorted(words) }))
+}
+
+pub async fn seed(
+    State(state): State<AppState>,
+    Path(project): Path<String>,
+    ApiJson(req): ApiJson<SeedReq>,
+) -> Result<Json<DictResp>, RouterError> {
+    check_project(&project)?;
+    let terms = seed_terms(&req.characters, &req.worldbuilding);
+    let words = state.user_dicts.add(&project, terms.iter().map(String::as_str)).map_err(io_error)?;
+    Ok(Json(DictResp { project, words: sorted(words) }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    #[test]
+    fn concurrent_adds_keep_every_word() {
+        let dir = tempfile::tempdir().unwrap();
+        let dicts = Arc::new(UserDictionaries::new(dir.path().to_path_buf()));
+        let threads: Vec<_> = (0..16)
+            .map(|i| {
+                let dicts = Arc::clone(&dicts);
+                std::thread::spawn(move || dicts.add("novel", [format!("word{}", i).as_str()]).unwrap())
+            })
+            .collect();
+        threads.into_iter().for_each(|t| {
+            t.join().unwrap();
+        });
+
+        let expected: HashSet<String> = (0..16).map(|i| format!("word{}", i)).collect();
+        assert_eq!(dicts.words("novel"), expected);
+        assert_eq!(UserDictionaries::new(dir.path().to_path_buf()).words("novel"), expected);
+    }
+}
@@ -0,0 +1,142 @@
+use serde::Serialize;
+use std::collections::{BTreeMap, HashSet};
+use std::ops::Range;
+use crate::guards::Change;
+use crate::heuristics::{self, Issue};
+
+// Checks a copy edit by running the heuristics over the text before and
+// after it: how the issue counts moved, and which edits brought in errors
+// the original did not have.
+
+// Issue kinds an edit must never introduce.
+const REGRESSIONS: &[&str] = &["emdash", "spelling", "contraction"];
+
+#[derive(Debug, Serialize)]
+pub struct KindDelta {
+    pub before: usize,
+    pub after: usize,
+    pub delta: i64,
+}
+
+pub struct Report {
+    // Issue counts per kind, over every kind found before or after.
+    pub delta: BTreeMap<String, KindDelta>,
+    // For each change, the new errors it introduces.
+    pub introduced: Vec<Vec<String>>,
+}
+
+// `changes` must not overlap; one that overlaps an earlier one is left out
+// of the revision.
+pub fn verify(text: &str, user_words: &HashSet<String>, changes: &[Change]) -> Report {
+    let (revision, placed) = apply(text, changes);
+    let before = heuristics::issues(text, user_words);
+    let after = heuristics::issues(&revision, user_words);
+
+    let mut delta: BTreeMap<String, KindDelta> = BTreeMap::new();
+    for (issues, after_side) in [(&before, false), (&after, true)] {
+        for issue in issues.iter() {
+            let entry = delta.entry(issue.kind.clone()).or_insert(KindDelta { before: 0, after: 0, delta: 0 });
+            if after_side {
+                entry.after += 1;
+            } else {
+                entry.before += 1;
+            }
+        }
+    }
+    for kind in delta.values_mut() {
+        kind.delta = kind.after as i64 - kind.before as i64;
+    }
+
+    let before = Located::new(text, &before);
+    let after = Located::new(&revision, &after);
+    let introduced = changes
+        .iter()
+        .zip(placed)
+        .map(|(change, placed)| {
+            let Some(placed) = placed else { return Vec::new() };
+            let mut had: Vec<(&str, &str)> = before.within(change.start..change.end).map(|(i, found)| (i.kind.as_str(), found)).collect();
+            let mut new_errors = Vec::new();
+            for (issue, found) in after.within(placed) {
+                match had.iter().position(|h| *h == (issue.kind.as_str(), found)) {
+                    Some(at) => {
+                        had.swap_remove(at);
+                    }
+                    None => new_errors.push(issue.message.clone()),
+                }
+            }
+            new_errors
+        })
+        .collect();
+
+    Report { delta, introduced }
+}
+
+// The new errors each change would bring in on its own, for alternatives
+// that may overlap. Only the lines a change touches are checked.
+pub fn each(text: &str, user_words: &HashSet<String>, changes: &[Change]) -> Vec<Vec<String>> {
+    let bytes: Vec<usize> = text.char_indices().map(|(i, _)| i).chain([text.len()]).collect();
+    changes
+        .iter()
+        .map(|change| {
+            let (start, end) = (bytes[change.start], bytes[change.end]);
+            let line_start = text[..start].rfind('\n').map_or(0, |p| p + 1);
+            let line_end = text[end..].find('\n').map_or(text.len(), |p| end + p);
+            let offset = text[..line_start].chars().count();
+            let local = Change { start: change.start - offset, end: change.end - offset, new: change.new };
+            verify(&text[line_start..line_end], user_words, &[local]).introduced.remove(0)
+        })
+        .collect()
+}
+
+// The text with the changes made, and the character range each change's
+// new text takes in it.
+fn apply(text: &str, changes: &[Change]) -> (String, Vec<Option<Range<usize>>>) {
+    let chars: Vec<char> = text.chars().collect();
+    let mut order: Vec<usize> = (0..changes.len()).collect();
+    order.sort_by_key(|&i| (changes[i].start, changes[i].end));
+
+    let mut placed = vec![None; changes.len()];
+    let mut revision = String::new();
+    let mut copied = 0;
+    let mut shift: i64 = 0;
+    for i in order {
+        let change = &changes[i];
+        if change.start < copied || change.end > chars.len() {
+            continue;
+        }
+        revision.extend(&chars[copied..change.start]);
+        revision.push_str(change.new);
+        let start = (change.start as i64 + shift) as usize;
+        let len = change.new.chars().count();
+        placed[i] = Some(start..start + len);
+        shift += len as i64 - (change.end - change.start) as i64;
+        copied = change.end;
+    }
+    revision.extend(&chars[copied..]);
+    (revision, placed)
+}
+
+// Regression-kind issues with their character ranges and the text they flag.
+struct Located<'a> {
+    issues: Vec<(&'a Issue, Range<usize>, &'a str)>,
+}
+
+impl<'a> Located<'a> {
+    fn new(text: &'a str, issues: &'a [Issue]) -> Self {
+        let chars = |byte: usize| text[..byte].chars().count();
+        let issues = issues
+            .iter()
+            .filter(|i| REGRESSIONS.contains(&i.kind.as_str()))
+            .map(|i| (i, chars(i.start)..chars(i.end), &text[i.start..i.end]))
+            .collect();
+        Located { issues }
+    }
+
+    // Issues touching `range`; an empty range touches what is on either side.
+    fn within(&self, range: Range<usize>) -> impl Iterator<Item = (&'a Issue, &'a str)> + '_ {
+        self.issues
+            .iter()
+            .filter(move |(_, r, _)| r.start <= range.end && range.start <= r.end)
+            .map(|(issue, _, found)| (*issue, *found))
+    }
+}
@@ -0,0 +1,226 @@
+use axum::{extract::State, Json};
+use futures_util::{stream, StreamExt, TryStreamExt};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use crate::edit_result;
+use crate::error::{require, ApiJson, RouterError};
+use crate::provider::{LlmError, LlmProvider};
+use crate::scheduler::{RouteReq, ServedBy, TaskKind};
+use crate::segment::{self, Span};
+use crate::speakers;
+use crate::tiers::{GenOptions, Tier};
+use crate::AppState;
+
+// Checks dialogue against the story bible: each character's lines go to the
+// model with their voice, personality and background, and the lines that do
+// not sound like them come back with a rewrite in their voice. Replies
+// follow core/schemas/voice-review.schema.json.
+
+pub static SCHEMA: Lazy<Value> =
+    Lazy::new(|| serde_json::from_str(include_str!("../../schemas/voice-review.schema.json")).expect("voice-review schema is valid JSON"));
+
+static VALIDATOR: Lazy<jsonschema::Validator> =
+    Lazy::new(|| jsonschema::validator_for(&SCHEMA).expect("voice-review schema compiles"));
+
+const MAX_LINES_PER_CALL: usize = 40;
+const MAX_PARALLEL_CALLS: usize = 2;
+
+// A story bible character, as the desktop app stores it.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Profile {
+    name: String,
+    #[serde(default)]
+    description: String,
+    #[serde(default)]
+    voice_tone: String,
+    #[serde(default)]
+    personality: String,
+    #[serde(default)]
+    background: String,
+}
+
+impl Profile {
+    fn has_voice(&self) -> bool {
+        [&self.voice_tone, &self.personality, &self.background].iter().any(|f| !f.trim().is_empty())
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct VoiceReq {
+    #[serde(default)]
+    model: String,
+    tier: Option<Tier>,
+    options: Option<GenOptions>,
+    request_id: Option<String>,
+    text: String,
+    characters: Vec<Profile>,
+}
+
+// A spoken line: 1-based line of the text and the character range of the
+// words inside the quotes.
+#[derive(Debug, Serialize)]
+pub struct SpokenLine {
+    line: usize,
+    start: usize,
+    end: usize,
+    text: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FlaggedLine {
+    speaker: String,
+    #[serde(flatten)]
+    spoken: SpokenLine,
+    issue: String,
+    // The words to put inside the quotes instead.
+    rewrite: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct VoiceResp {
+    request_id: String,
+    // Lines checked against a profile.
+    checked: usize,
+    flagged: Vec<FlaggedLine>,
+    // Lines no speaker could be found for.
+    unattributed: Vec<SpokenLine>,
+    #[serde(flatten)]
+    served_by: ServedBy,
+}
+
+#[derive(Deserialize)]
+struct Verdict {
+    line: usize,
+    in_voice: bool,
+    #[serde(default)]
+    issue: String,
+    #[serde(default)]
+    rewrite: String,
+}
+
+#[derive(Deserialize)]
+struct Review {
+    lines: Vec<Verdict>,
+}
+
+fn spoken(text: &str, span: Span) -> SpokenLine {
+    let inner = segment::dialogue_inner(text, span);
+    let start = text[..inner.start].chars().count();
+    let words = &text[inner.start..inner.end];
+    SpokenLine {
+        line: 1 + text[..inner.start].matches('\n').count(),
+        start,
+        end: start + words.chars().count(),
+        text: words.to_string(),
+    }
+}
+
+fn voice_prompt(profile: &Profile, lines: &[SpokenLine]) -> String {
+    let sheet: String = [
+        ("Description", &profile.description),
+        ("Voice", &profile.voice_tone),
+        ("Personality", &profile.personality),
+        ("Background", &profile.background),
+    ]
+    .iter()
+    .filter(|(_, value)| !value.trim().is_empty())
+    .map(|(label, value)| format!("{}: {}\n", label, value.trim()))
+    .collect();
+    let numbered: String = lines.iter().enumerate().map(|(i, l)| format!("{}| {}\n", i + 1, l.text)).collect();
+
+    format!(
+        "SYSTEM: You are a dialogue editor checking that every line a character speaks sounds like them.
+
+CHARACTER: {}
+{}
+INSTRUCTIONS:
+Each numbered line below is spoken by {}. Decide for every line whether its word choice, rhythm and attitude fit the character's voice, personality and background. Reply with a JSON object {{\"lines\": [...]}} holding one entry per line: {{\"line\": the number, \"in_voice\": true or false, \"issue\": for a line out of voice, one sentence on what sounds wrong, \"rewrite\": for a line out of voice, the line reworded in the character's voice with the same meaning}}.
+
+LINES:
+{}",
+        profile.name, sheet, profile.name, numbered
+    )
+}
+
+// The flagged lines of one batch; `lines` are the batch, in prompt order.
+async fn review(llm: &dyn LlmProvider, model: &str, options: &GenOptions, profile: &Profile, lines: &[SpokenLine]) -> Result<Vec<(usize, String, String)>, LlmError> {
+    let reply = llm.generate_json(model, &voice_prompt(profile, lines), &SCHEMA, options).await?;
+    let review: Review = edit_result::parse(&reply, &VALIDATOR).map_err(|e| LlmError::Upstream(format!("unusable voice review from the model: {}", e)))?;
+    Ok(review
+        .lines
+        .into_iter()
+        .filter(|v| !v.in_voice && !v.rewrite.trim().is_empty() && (1..=lines.len()).contains(&v.line))
+        .map(|v| (v.line - 1, v.issue, v.rewrite.trim().to_string()))
+        .collect())
+}
+
+pub async fn review_dialogue(State(state): State<AppState>, ApiJson(req): ApiJson<VoiceReq>) -> Result<Json<VoiceResp>, RouterError> {
+    require(&req.text, "text")?;
+    let names: Vec<&str> = req.characters.iter().map(|c| c.name.as_str()).collect();
+    let quotes = speakers::attribute(&req.text, &names);
+
+    // Each speaker's lines in text order, then cut into batches.
+    let mut by_speaker: Vec<Vec<SpokenLine>> = req.characters.iter().map(|_| Vec::new()).collect();
+    let mut unattributed = Vec::new();
+    for quote in &quotes {
+        let line = spoken(&req.text, quote.span);
+        if line.text.trim().is_empty() {
+            continue;
+        }
+        match quote.speaker {
+            Some(speaker) => by_speaker[speaker].push(line),
+            None => unattributed.push(line),
+        }
+    }
+    let mut batches: Vec<(usize, Vec<SpokenLine>)> = Vec::new();
+    for (speaker, mut lines) in by_speaker.into_iter().enumerate() {
+        if !req.characters[speaker].has_voice() {
+            continue;
+        }
+        while !lines.is_empty() {
+            let rest = lines.split_off(lines.len().min(MAX_LINES_PER_CALL));
+            batches.push((speaker, lines));
+            lines = rest;
+        }
+    }
+    let checked = batches.iter().map(|(_, lines)| lines.len()).sum();
+
+    let route = RouteReq {
+        task: TaskKind::CopyEdit,
+        // Each call sees one batch, so the largest one decides the tier.
+        input_size: batches.iter().map(|(_, lines)| lines.iter().map(|l| l.text.chars().count()).sum()).max().unwrap_or(0),
+        tier: req.tier,
+        model: &req.model,
+        options: req.options.as_ref(),
+    };
+    let routed = state.scheduler.route(state.llm.as_ref(), &state.tiers, route).await?;
+    let llm = state.llm.as_ref();
+    let (model, options) = (routed.model(), &routed.options);
+
+    let job = state.jobs.start(req.request_id.clone());
+    let outcome = job
+        .run(
+            stream::iter(0..batches.len())
+                .map(|i| review(llm, model, options, &req.characters[batches[i].0], &batches[i].1))
+                .buffered(MAX_PARALLEL_CALLS)
+                .try_collect::<Vec<_>>(),
+        )
+        .await;
+    let request_id = job.id.clone();
+    job.finish();
+    let verdicts = outcome??;
+
+    let mut flagged = Vec::new();
+    for ((speaker, lines), found) in batches.into_iter().zip(verdicts) {
+        let mut spoken: Vec<Option<SpokenLine>> = lines.into_iter().map(Some).collect();
+        for (i, issue, rewrite) in found {
+            if let Some(spoken) = spoken[i].take() {
+                flagged.push(FlaggedLine { speaker: req.characters[speaker].name.clone(), spoken, issue, rewrite });
+            }
+        }
+    }
+    flagged.sort_by_key(|f| f.spoken.start);
+    Ok(Json(VoiceResp { request_id, checked, flagged, unattributed, served_by: routed.served_by }))
+}
@@ -0,0 +1,680 @@
+use axum::body::Body;
+use axum::http::{Request, StatusCode};
+use axum::Router;
+use http_body_util::BodyExt;
+use router::mock_client::{MockFault, MockProvider};
+use router::tiers::Tiers;
+use router::{app, AppState};
+use serde_json::{json, Value};
+use std::sync::Arc;
+use std::time::Duration;
+use tempfile::TempDir;
+use tower::ServiceExt;
+
+// Exercises the whole router against the in-process mock backend, so no
+// model server is needed.
+
+fn test_app(mock: MockProvider) -> (Router, Arc<MockProvider>, TempDir) {
+    let data_dir = tempfile::tempdir().unwrap();
+    let mock = Arc::new(mock);
+    let state = AppState::new(data_dir.path(), mock.clone(), Tiers::default());
+    (app::router(state), mock, data_dir)
+}
+
+async fn send(app: &Router, method: &str, uri: &str, body: Option<Value>) -> (StatusCode, String) {
+    let req = Request::builder().method(method).uri(uri).header("content-type", "application/json");
+    let req = match body {
+        Some(body) => req.body(Body::from(body.to_string())).unwrap(),
+        None => req.body(Body::empty()).unwrap(),
+    };
+    let resp = app.clone().oneshot(req).await.unwrap();
+    let status = resp.status();
+    let bytes = resp.into_body().collect().await.unwrap().to_bytes();
+    (status, String::from_utf8(bytes.to_vec()).unwrap())
+}
+
+async fn post(app: &Router, uri: &str, body: Value) -> (StatusCode, Value) {
+    let (status, text) = send(app, "POST", uri, Some(body)).await;
+    (status, serde_json::from_str(&text).unwrap_or(Value::Null))
+}
+
+async fn get(app: &Router, uri: &str) -> (StatusCode, Value) {
+    let (status, text) = send(app, "GET", uri, None).await;
+    (status, serde_json::from_str(&text).unwrap_or(Value::Null))
+}
+
+// (event, data) pairs from a Server-Sent Events body.
+fn sse_events(body: &str) -> Vec<(String, Value)> {
+    body.split("\n\n")
+        .filter_map(|block| {
+            let event = block.lines().find_map(|l| l.strip_prefix("event: "))?;
+            let data = block.lines().find_map(|l| l.strip_prefix("data: ")).unwrap_or("null");
+            Some((event.to_string(), serde_json::from_str(data).unwrap_or(Value::Null)))
+        })
+        .collect()
+}
+
+fn minor_edit_body(text: &str) -> Value {
+    json!({"model": "test", "style": {}, "text": text, "citations": []})
+}
+
+#[tokio::test]
+async fn minor_edit_reports_changed_lines() {
+    let (app, _, _dir) = test_app(MockProvider::scripted(["He walked home.\nIt was late."]));
+    let (status, body) = post(&app, "/api/minor_edit", minor_edit_body("He was very tired.\nIt was late.")).await;
+
+    assert_eq!(status, StatusCode::OK);
+    let edits = body["edits"].as_array().unwrap();
+    assert_eq!(edits.len(), 1);
+    assert_eq!(edits[0]["kind"], "replace");
+    assert_eq!(edits[0]["line"], 1);
+    assert_eq!((edits[0]["start"].as_u64(), edits[0]["end"].as_u64()), (Some(3), Some(17)));
+    assert_eq!(edits[0]["old"], "was very tired");
+    assert_eq!(edits[0]["new"], "walked home");
+}
+
+#[tokio::test]
+async fn merged_paragraphs_do_not_shift_later_edits() {
+    let text = "Ana wept.\nBo laughed.\nThe rain stopped.\nIt was late.";
+    let revised = "Ana wept. Bo laughed.\nThe rain stopped.\nIt was very late.\nThe end.";
+    let (app, _, _dir) = test_app(MockProvider::scripted([revised]));
+    let (_, body) = post(&app, "/api/minor_edit", minor_edit_body(text)).await;
+
+    let edits = body["edits"].as_array().unwrap();
+    let summary: Vec<(&str, u64, u64, u64, &str, &str)> = edits
+        .iter()
+        .map(|e| {
+            let n = |k: &str| e[k].as_u64().unwrap();
+            (e["kind"].as_str().unwrap(), n("line"), n("start"), n("end"), e["old"].as_str().unwrap(), e["new"].as_str().unwrap())
+        })
+        .collect();
+    assert_eq!(
+        summary,
+        [
+            ("replace", 1, 9, 10, "\n", " "),
+            ("insert", 4, 47, 47, "", "very "),
+            ("insert", 4, 52, 52, "", "\nThe end."),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn long_texts_are_edited_in_chunks_that_keep_their_line_numbers() {
+    let paragraph = |n: usize| format!("Paragraph {} {}", n, "word ".repeat(150).trim_end());
+    let text = (1..=4).map(paragraph).collect::<Vec<_>>().join("\n\n");
+    let replies = (1..=4).map(|n| json!({"edits": [{"line": 2 * n - 1, "old": format!("Paragraph {}", n), "new": format!("Part {}", n)}]}).to_string());
+    let (app, mock, _dir) = test_app(MockProvider::scripted(replies));
+
+    let mut body = minor_edit_body(&text);
+    body["options"] = json!({"num_ctx": 512});
+    let (status, body) = post(&app, "/api/minor_edit", body).await;
+    assert_eq!(status, StatusCode::OK);
+
+    let requests = mock.requests();
+    assert_eq!(requests.len(), 4);
+    assert!(requests[1].messages[0].content.contains(&format!("3| {}", paragraph(2))));
+    assert!(!requests[1].messages[0].content.contains(&paragraph(3)));
+    assert!(requests[1].messages[0].content.contains("PRECEDING TEXT (original"));
+    assert!(!requests[0].messages[0].content.contains("PRECEDING TEXT"));
+
+    let edits: Vec<(u64, u64, &str)> = body["edits"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|e| (e["line"].as_u64().unwrap(), e["start"].as_u64().unwrap(), e["new"].as_str().unwrap()))
+        .collect();
+    let stride = paragraph(1).len() as u64 + 2;
+    assert_eq!(edits, [(1, 0, "Part 1"), (3, stride, "Part 2"), (5, 2 * stride, "Part 3"), (7, 3 * stride, "Part 4")]);
+
+    // Streaming plays plain revisions of the chunks in order; a chunk that
+    // grows a line does not move the edits after it.
+    let revised = |n: usize| paragraph(n).replace("Paragraph", "Part");
+    let replies = [format!("{}\nAn extra line.", revised(1)), revised(2), revised(3), revised(4)];
+    let (app, _, _dir) = test_app(MockProvider::scripted(replies.clone()));
+    let mut stream_body = minor_edit_body(&text);
+    stream_body["options"] = json!({"num_ctx": 512});
+    let (_, stream) = send(&app, "POST", "/api/minor_edit/stream", Some(stream_body)).await;
+    let events = sse_events(&stream);
+    let tokens: String = events.iter().filter(|(e, _)| e == "token").map(|(_, d)| d["text"].as_str().unwrap()).collect();
+    assert_eq!(tokens, replies.join("\n"));
+    let (_, done) = events.last().unwrap();
+    let edits: Vec<(u64, u64, &str)> = done["edits"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|e| (e["line"].as_u64().unwrap(), e["start"].as_u64().unwrap(), e["new"].as_str().unwrap()))
+        .collect();
+    assert_eq!(edits, [(1, 0, "Part"), (2, stride - 1, "An extra line.\n"), (3, stride, "Part"), (5, 2 * stride, "Part"), (7, 3 * stride, "Part")]);
+}
+
+#[tokio::test]
+async fn minor_edit_asks_for_structured_edits_and_falls_back_to_a_diff() {
+    let text = "He was very tired.\nIt was late, and the road was long.";
+    let reply = json!({"edits": [
+        {"line": 2, "old": "was late", "new": "had grown late", "citations": ["weak-verbs"], "rationale": "A more specific verb."},
+        {"line": 1, "old": "very tired", "new": "exhausted", "citations": ["filler", "house-style-4"], "rationale": "Cuts a filler word."},
+        {"line": 1, "old": "not in the text", "new": "anything"},
+    ]});
+    let (app, mock, _dir) = test_app(MockProvider::scripted([reply.to_string()]));
+    let mut body = minor_edit_body(text);
+    body["citations"] = json!(["house-style-4"]);
+    let (status, body) = post(&app, "/api/minor_edit", body).await;
+    assert_eq!(status, StatusCode::OK);
+
+    let requests = mock.requests();
+    assert_eq!(requests.len(), 1);
+    assert!(requests[0].format.is_some());
+    assert!(requests[0].messages[0].content.contains("[house-style-4]"));
+    assert!(requests[0].messages[0].content.contains("2| It was late"));
+
+    let edits = body["edits"].as_array().unwrap();
+    assert_eq!(edits.len(), 2);
+    assert_eq!((edits[0]["line"].as_u64(), edits[0]["start"].as_u64(), edits[0]["end"].as_u64()), (Some(1), Some(7), Some(17)));
+    assert_eq!(edits[0]["new"], "exhausted");
+    assert_eq!(edits[0]["citations"], json!(["filler", "house-style-4"]));
+    assert_eq!(edits[0]["rationale"], "Cuts a filler word.");
+    assert_eq!((edits[1]["line"].as_u64(), edits[1]["start"].as_u64()), (Some(2), Some(22)));
+    assert_eq!(edits[1]["old"], "was late");
+
+    // A reply missing "new" does not match the schema, so the edit is
+    // redone as a plain revision and diffed.
+    let bad = json!({"edits": [{"line": 1, "old": "very tired"}]}).to_string();
+    let (app, mock, _dir) = test_app(MockProvider::scripted([bad, "He was exhausted.\nIt was late, and the road was long.".into()]));
+    let (_, body) = post(&app, "/api/minor_edit", minor_edit_body(text)).await;
+    assert_eq!(mock.requests().len(), 2);
+    assert!(mock.requests()[1].format.is_none());
+    assert_eq!(body["edits"][0]["old"], "very tired");
+    assert_eq!(body["edits"][0]["new"], "exhausted");
+}
+
+#[tokio::test]
+async fn guards_refuse_rewrites_new_names_and_dropped_dialogue() {
+    let text = "Anna waited by the door while the rain kept falling on the quiet street outside.\n\"Are you coming?\" she asked.\nShe was very tired.\nTom shrugged.";
+    let reply = json!({"edits": [
+        {"line": 1, "old": "waited by the door while the rain kept falling on the quiet street outside", "new": "paced the hall as thunder shook every window in the old house"},
+        {"line": 2, "old": "\"Are you coming?\" she asked.", "new": "She asked him to come."},
+        {"line": 3, "old": "She was very tired", "new": "She and Marcus were tired"},
+        {"line": 4, "old": "Tom shrugged", "new": "Tom and Bea shrugged"},
+    ]});
+    let (app, _, _dir) = test_app(MockProvider::scripted([reply.to_string()]));
+    let mut body = minor_edit_body(text);
+    body["characters"] = json!(["Bea Lorne"]);
+    let (status, body) = post(&app, "/api/minor_edit", body).await;
+    assert_eq!(status, StatusCode::OK);
+
+    let edits = body["edits"].as_array().unwrap();
+    assert_eq!(edits.len(), 1);
+    assert_eq!(edits[0]["new"], "Tom and Bea shrugged");
+    let refused: Vec<(u64, &str)> =
+        body["refused"].as_array().unwrap().iter().map(|e| (e["line"].as_u64().unwrap(), e["reason"].as_str().unwrap())).collect();
+    assert_eq!(refused.len(), 3);
+    assert_eq!(refused[0].0, 1);
+    assert!(refused[0].1.contains("of the paragraph"));
+    assert_eq!(refused[1], (2, "drops a line of dialogue"));
+    assert_eq!(refused[2], (3, "introduces \"Marcus\", a name not in the text or cast list"));
+}
+
+#[tokio::test]
+async fn edits_are_checked_by_rerunning_the_heuristics() {
+    let text = "He was very tired.\nThe road was long.\nShe did not stop.";
+    let reply = json!({"edits": [
+        {"line": 1, "old": "very tired", "new": "tired"},
+        {"line": 2, "old": "long", "new": "lnog"},
+        {"line": 3, "old": "did not stop", "new": "didn't stop—she ran"},
+    ]})
+    .to_string();
+    let (app, _, _dir) = test_app(MockProvider::scripted([reply.clone(), reply]));
+
+    let (status, body) = post(&app, "/api/minor_edit", minor_edit_body(text)).await;
+    assert_eq!(status, StatusCode::OK);
+    let edits = body["edits"].as_array().unwrap();
+    assert_eq!(edits.len(), 3);
+    assert!(edits[0].get("introduces").is_none());
+    assert_eq!(edits[1]["introduces"].as_array().unwrap().len(), 1);
+    assert!(edits[1]["introduces"][0].as_str().unwrap().contains("'lnog'"));
+    assert_eq!(edits[2]["introduces"].as_array().unwrap().len(), 2);
+    assert_eq!(body["issue_delta"]["filler"], json!({"before": 1, "after": 0, "delta": -1}));
+    assert_eq!(body["issue_delta"]["emdash"]["delta"], 1);
+    assert_eq!(body["issue_delta"]["contraction"]["delta"], 1);
+
+    let mut request = minor_edit_body(text);
+    request["drop_regressions"] = json!(true);
+    let (_, body) = post(&app, "/api/minor_edit", request).await;
+    assert_eq!(body["edits"].as_array().unwrap().len(), 1);
+    let refused = body["refused"].as_array().unwrap();
+    assert_eq!(refused.len(), 2);
+    assert!(refused.iter().all(|r| r["reason"].as_str().unwrap().starts_with("introduces errors: ")));
+    assert_eq!(body["issue_delta"]["filler"]["delta"], -1);
+    assert!(body["issue_delta"].get("emdash").is_none());
+}
+
+#[tokio::test]
+async fn targeted_edits_fix_each_flagged_span_on_its_own() {
+    let text = "He was very tired. She walked slowly home.\nIt was late.";
+    let issue = |kind: &str, word: &str, message: &str| {
+        let start = text.find(word).unwrap();
+        json!({"kind": kind, "start": start, "end": start + word.len(), "message": message, "severity": "info"})
+    };
+    let issues = json!([
+        issue("filler", "very", "Filler word"),
+        issue("adverb", "slowly", "Adverb - consider a stronger verb"),
+        issue("weak_verb", "was late", "Weak verb"),
+    ]);
+    let replies = ["He was tired.", "\"She walked home.\"", "It was late."];
+    let (app, mock, _dir) = test_app(MockProvider::scripted(replies));
+    let (status, body) = post(&app, "/api/minor_edit/targeted", json!({"model": "test", "text": text, "issues": issues})).await;
+    assert_eq!(status, StatusCode::OK);
+
+    let requests = mock.requests();
+    assert_eq!(requests.len(), 3);
+    assert!(requests[0].messages[0].content.contains("PROBLEM (filler): Filler word"));
+    assert!(requests[0].messages[0].content.ends_with("SENTENCE:\nHe was very tired."));
+    assert!(requests[1].messages[0].content.ends_with("SENTENCE:\nShe walked slowly home."));
+
+    let candidates: Vec<(u64, &str, u64, u64, &str, &str)> = body["candidates"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|c| {
+            (c["issue"].as_u64().unwrap(), c["kind"].as_str().unwrap(), c["start"].as_u64().unwrap(), c["end"].as_u64().unwrap(), c["old"].as_str().unwrap(), c["new"].as_str().unwrap())
+        })
+        .collect();
+    assert_eq!(candidates, [(0, "delete", 7, 12, "very ", ""), (1, "delete", 30, 37, "slowly ", "")]);
+    assert_eq!(body["candidates"][1]["citations"], json!(["adverb"]));
+    assert_eq!(body["unchanged"], json!([2]));
+
+    let bad = json!({"model": "test", "text": text, "issues": [{"kind": "filler", "start": 40, "end": 90, "message": "x"}]});
+    let (status, _) = post(&app, "/api/minor_edit/targeted", bad).await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+
+    let many = vec![json!({"kind": "filler", "start": 7, "end": 12, "message": "Filler word"}); 51];
+    let (status, body) = post(&app, "/api/minor_edit/targeted", json!({"model": "test", "text": text, "issues": many})).await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert!(body["message"].as_str().unwrap().contains("at most 50 issues"));
+    assert_eq!(mock.requests().len(), 3);
+}
+
+#[tokio::test]
+async fn edits_are_rebased_onto_text_written_since() {
+    let base = "He was very tired.\nIt was late, and the road was long.";
+    let reply = json!({"edits": [
+        {"line": 1, "old": "very tired", "new": "exhausted"},
+        {"line": 2, "old": "was long", "new": "stretched on"},
+    ]});
+    let (app, _, _dir) = test_app(MockProvider::scripted([reply.to_string()]));
+    let (_, edited) = post(&app, "/api/minor_edit", minor_edit_body(base)).await;
+    assert_eq!(edited["edits"].as_array().unwrap().len(), 2);
+
+    let text = "By then he was very tired.\nIt was late, and the road was endless.";
+    let request = json!({"base_hash": edited["base_hash"], "text": text, "edits": edited["edits"]});
+    let (status, body) = post(&app, "/api/minor_edit/rebase", request).await;
+    assert_eq!(status, StatusCode::OK);
+    let edits = body["edits"].as_array().unwrap();
+    assert_eq!(edits.len(), 1);
+    assert_eq!((edits[0]["line"].as_u64(), edits[0]["start"].as_u64(), edits[0]["end"].as_u64()), (Some(1), Some(15), Some(25)));
+    assert_eq!(&text[15..25], "very tired");
+    let conflicts = body["conflicts"].as_array().unwrap();
+    assert_eq!(conflicts.len(), 1);
+    assert_eq!(conflicts[0]["old"], "was long");
+    assert_eq!(conflicts[0]["current"]["text"], "was endless");
+
+    // A base the router no longer remembers has to be sent along.
+    let (status, _) = post(&app, "/api/minor_edit/rebase", json!({"base_hash": "0", "text": text, "edits": []})).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    let request = json!({"base_hash": body["base_hash"], "base": base, "text": text, "edits": []});
+    let (status, _) = post(&app, "/api/minor_edit/rebase", request).await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+}
+
+#[tokio::test]
+async fn rewrite_offers_scored_alternatives_per_intent() {
+    let paragraph = "She walked very slowly back. It was late.";
+    let passage = "walked very slowly back";
+    let start = paragraph.find(passage).unwrap();
+    let replies = ["moved back", "\"ran back\"", passage, "wnet back"];
+    let (app, mock, _dir) = test_app(MockProvider::scripted(replies));
+    let request = json!({"model": "test", "paragraph": paragraph, "start": start, "end": start + passage.len()});
+    let (status, body) = post(&app, "/api/rewrite", request).await;
+    assert_eq!(status, StatusCode::OK);
+
+    let requests = mock.requests();
+    assert_eq!(requests.len(), 4);
+    assert!(requests[2].messages[0].content.contains("More tension"));
+    assert!(requests[2].messages[0].content.ends_with(&format!("PASSAGE:\n{}", passage)));
+
+    let alternatives = body["alternatives"].as_array().unwrap();
+    let offered: Vec<(&str, &str)> = alternatives.iter().map(|a| (a["intent"].as_str().unwrap(), a["text"].as_str().unwrap())).collect();
+    assert_eq!(offered, [("tighter", "moved back"), ("more_vivid", "ran back"), ("simpler", "wnet back")]);
+    assert!(alternatives[0]["scores"]["delta"].as_i64().unwrap() < 0);
+    assert_eq!(alternatives[0]["scores"]["by_kind"]["filler"]["after"], 0);
+    assert!(alternatives[0]["scores"].get("introduces").is_none());
+    assert!(alternatives[2]["scores"]["introduces"][0].as_str().unwrap().contains("'wnet'"));
+
+    let request = json!({"model": "test", "paragraph": paragraph, "start": 10, "end": 10});
+    let (status, _) = post(&app, "/api/rewrite", request).await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+
+    let request = json!({"model": "test", "paragraph": paragraph, "start": start, "end": start + passage.len(), "intents": vec!["tighter"; 100]});
+    let (status, _) = post(&app, "/api/rewrite", request).await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(mock.requests().len(), 4);
+}
+
+#[tokio::test]
+async fn dialogue_is_checked_against_each_speakers_voice() {
+    let text = "\"Hey, what's up?\" Anna said.\n\"I would be delighted to assist you, madam,\" Tom replied.\n\"Cool.\"\nAnna Vance shrugged. \"Whatever works.\"\n\"Who cares,\" someone muttered.";
+    let characters = json!([
+        {"id": "1", "name": "Anna Vance", "voiceTone": "Blunt and slangy", "personality": "Impatient", "background": "", "characterType": "protagonist"},
+        {"id": "2", "name": "Tom", "voiceTone": "Gruff, few words", "personality": "", "background": "Dock worker"},
+        {"id": "3", "name": "Mara", "voiceTone": "", "personality": "", "background": ""},
+    ]);
+    let replies = [
+        json!({"lines": [{"line": 1, "in_voice": true}, {"line": 2, "in_voice": true}, {"line": 3, "in_voice": true}]}).to_string(),
+        json!({"lines": [{"line": 1, "in_voice": false, "issue": "Far too formal for Tom.", "rewrite": "Fine. I'll help,"}]}).to_string(),
+    ];
+    let (app, mock, _dir) = test_app(MockProvider::scripted(replies));
+    let (status, body) = post(&app, "/api/dialogue/voice", json!({"model": "test", "text": text, "characters": characters})).await;
+    assert_eq!(status, StatusCode::OK);
+
+    let requests = mock.requests();
+    assert_eq!(requests.len(), 2);
+    let anna = &requests[0].messages[0].content;
+    assert!(anna.contains("CHARACTER: Anna Vance") && anna.contains("Voice: Blunt and slangy"));
+    assert!(anna.contains("1| Hey, what's up?\n2| Cool.\n3| Whatever works."));
+    assert!(!anna.contains("Background:"));
+    assert!(requests[0].format.is_some());
+    assert!(requests[1].messages[0].content.contains("1| I would be delighted to assist you, madam,"));
+
+    assert_eq!(body["checked"], 4);
+    let flagged = body["flagged"].as_array().unwrap();
+    assert_eq!(flagged.len(), 1);
+    assert_eq!(flagged[0]["speaker"], "Tom");
+    assert_eq!(flagged[0]["line"], 2);
+    assert_eq!(flagged[0]["rewrite"], "Fine. I'll help,");
+    let start = flagged[0]["start"].as_u64().unwrap() as usize;
+    assert_eq!(&text[start..start + 42], "I would be delighted to assist you, madam,");
+    let unattributed = body["unattributed"].as_array().unwrap();
+    assert_eq!(unattributed.len(), 1);
+    assert_eq!((unattributed[0]["line"].as_u64(), unattributed[0]["text"].as_str()), (Some(5), Some("Who cares,")));
+}
+
+#[tokio::test]
+async fn minor_edit_reports_backend_failure() {
+    let (app, _, _dir) = test_app(MockProvider::echo().with_fault(MockFault::Error("model not found".into())));
+    let (status, body) = post(&app, "/api/minor_edit", minor_edit_body("It was late.")).await;
+
+    assert_eq!(status, StatusCode::BAD_GATEWAY);
+    assert_eq!(body["code"], "upstream_error");
+    assert_eq!(body["retryable"], false);
+    assert!(body["message"].as_str().unwrap().contains("model not found"));
+}
+
+#[tokio::test]
+async fn bad_input_is_rejected_before_calling_the_model() {
+    let (app, mock, _dir) = test_app(MockProvider::echo());
+    let (status, body) = post(&app, "/api/minor_edit", json!({"model": "test", "style": {}, "text": "  "})).await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(body["code"], "bad_input");
+
+    let (status, body) = post(&app, "/api/chat", json!({"model": "test", "prompt": ""})).await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert!(body["message"].as_str().unwrap().contains("prompt"));
+    assert!(mock.requests().is_empty());
+}
+
+#[tokio::test]
+async fn minor_edit_stream_ends_with_edits() {
+    let (app, _, _dir) = test_app(MockProvider::scripted(["She ran home."]));
+    let (status, body) = send(&app, "POST", "/api/minor_edit/stream", Some(minor_edit_body("She went home."))).await;
+
+    assert_eq!(status, StatusCode::OK);
+    let events = sse_events(&body);
+    assert_eq!(events.first().unwrap().0, "job");
+    let tokens: String = events.iter().filter(|(e, _)| e == "token").map(|(_, d)| d["text"].as_str().unwrap()).collect();
+    assert_eq!(tokens, "She ran home.");
+    let (event, done) = events.last().unwrap();
+    assert_eq!(event, "done");
+    assert_eq!(done["edits"][0]["old"], "went");
+    assert_eq!(done["edits"][0]["new"], "ran");
+}
+
+#[tokio::test]
+async fn chat_sends_system_context_and_prompt() {
+    let (app, mock, _dir) = test_app(MockProvider::echo());
+    let (status, body) = post(&app, "/api/chat", json!({"model": "test", "prompt": "Name my villain.", "context": "Chapter one."})).await;
+
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["response"], "Name my villain.");
+    assert!(body.get("session_id").is_none());
+
+    let sent = &mock.requests()[0].messages;
+    assert_eq!(sent[0].role, "system");
+    assert!(sent[0].content.contains("Chapter one."));
+    assert_eq!(sent.last().unwrap().content, "Name my villain.");
+}
+
+#[tokio::test]
+async fn tier_defaults_apply_and_requests_can_override_them() {
+    let (app, mock, _dir) = test_app(MockProvider::echo());
+    post(&app, "/api/chat", json!({"tier": "large", "prompt": "Brainstorm."})).await;
+    post(&app, "/api/chat", json!({"model": "llama3.2:3b", "prompt": "Summarize.", "options": {"temperature": 1.2, "stop": ["END"]}})).await;
+
+    let requests = mock.requests();
+    let large = Tiers::default().large;
+    assert_eq!(requests[0].model, large.model);
+    assert_eq!(requests[0].options, large.options);
+
+    let small = Tiers::default().small;
+    assert_eq!(requests[1].model, "llama3.2:3b");
+    assert_eq!(requests[1].options.temperature, Some(1.2));
+    assert_eq!(requests[1].options.num_ctx, small.options.num_ctx);
+    assert_eq!(requests[1].options.stop, ["END"]);
+
+    let (status, body) = post(&app, "/api/chat", json!({"tier": "small", "prompt": "Hi", "options": {"num_ctx": 10_000_000}})).await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert!(body["message"].as_str().unwrap().contains("num_ctx"));
+    assert_eq!(mock.requests().len(), 2);
+}
+
+#[tokio::test]
+async fn requests_are_routed_by_task_and_input_size() {
+    let (app, mock, _dir) = test_app(MockProvider::echo());
+    let tiers = Tiers::default();
+
+    let (_, body) = post(&app, "/api/chat", json!({"prompt": "Hi"})).await;
+    assert_eq!(body["tier"], "small");
+    assert_eq!(body["model"], tiers.small.model.as_str());
+
+    let (_, body) = post(&app, "/api/chat", json!({"task": "rewrite", "prompt": "Rewrite this."})).await;
+    assert_eq!(body["tier"], "large");
+
+    // Too much text for the small tier's context window.
+    let (_, body) = post(&app, "/api/chat", json!({"prompt": "Summarize.", "context": "word ".repeat(3000)})).await;
+    assert_eq!(body["tier"], "medium");
+
+    let (_, body) = post(&app, "/api/minor_edit", json!({"style": {}, "text": "He was cold."})).await;
+    assert_eq!(body["tier"], "medium");
+    assert_eq!(body["model"], tiers.medium.model.as_str());
+
+    // An explicit model is used as given.
+    let (_, body) = post(&app, "/api/chat", json!({"model": "custom:1b", "task": "rewrite", "prompt": "Hi"})).await;
+    assert_eq!(body["model"], "custom:1b");
+    assert!(body.get("fallback_from").is_none());
+
+    let models: Vec<String> = mock.requests().into_iter().map(|r| r.model).collect();
+    // The echoed prompt is not JSON, so the copy edit is retried as a plain revision.
+    assert_eq!(models, [&tiers.small.model, &tiers.large.model, &tiers.medium.model, &tiers.medium.model, &tiers.medium.model, "custom:1b"]);
+}
+
+#[tokio::test]
+async fn missing_tier_models_fall_back_to_a_smaller_installed_one() {
+    let tiers = Tiers::default();
+    let (app, mock, _dir) = test_app(MockProvider::echo().with_models([tiers.small.model.as_str()]));
+
+    let (status, body) = post(&app, "/api/chat", json!({"task": "rewrite", "prompt": "Rewrite this."})).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["tier"], "small");
+    assert_eq!(body["model"], tiers.small.model.as_str());
+    assert_eq!(body["fallback_from"], tiers.large.model.as_str());
+    assert_eq!(mock.requests()[0].options, tiers.small.options);
+
+    let (status, body) = send(&app, "POST", "/api/minor_edit/stream", Some(json!({"style": {}, "text": "He was cold."}))).await;
+    assert_eq!(status, StatusCode::OK);
+    let events = sse_events(&body);
+    let (_, done) = events.iter().find(|(e, _)| e == "done").unwrap();
+    assert_eq!(done["model"], tiers.small.model.as_str());
+    assert_eq!(done["fallback_from"], tiers.medium.model.as_str());
+
+    // The small tier cannot hold this much text, so there is nothing to fall back to.
+    let (status, body) = post(&app, "/api/chat", json!({"prompt": "Summarize.", "context": "word ".repeat(3000)})).await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert!(body["message"].as_str().unwrap().contains(tiers.medium.model.as_str()));
+    assert_eq!(mock.requests().len(), 2);
+}
+
+#[tokio::test]
+async fn chat_session_history_is_saved_and_replayed() {
+    let (app, mock, _dir) = test_app(MockProvider::scripted(["First answer.", "Second answer."]));
+    let (_, first) = post(&app, "/api/chat", json!({"model": "test", "prompt": "First question?", "project": "novel"})).await;
+    let session = first["session_id"].as_str().unwrap().to_string();
+
+    let (_, second) = post(&app, "/api/chat", json!({"model": "test", "prompt": "Second question?", "project": "novel", "session_id": session})).await;
+    assert_eq!(second["response"], "Second answer.");
+
+    let sent: Vec<(String, String)> = mock.requests()[1].messages.iter().map(|m| (m.role.clone(), m.content.clone())).collect();
+    assert_eq!(sent[1..], [
+        ("user".to_string(), "First question?".to_string()),
+        ("assistant".to_string(), "First answer.".to_string()),
+        ("user".to_string(), "Second question?".to_string()),
+    ]);
+
+    let (_, sessions) = get(&app, "/api/chat/sessions/novel").await;
+    assert_eq!(sessions[0]["id"], session.as_str());
+    assert_eq!(sessions[0]["title"], "First question?");
+    assert_eq!(sessions[0]["message_count"], 4);
+
+    let (status, _) = send(&app, "DELETE", &format!("/api/chat/sessions/novel/{}", session), None).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    let (status, _) = get(&app, &format!("/api/chat/sessions/novel/{}", session)).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn chat_stream_reports_malformed_output() {
+    let (app, _, _dir) = test_app(MockProvider::scripted(["one two three four"]).with_fault(MockFault::Malformed));
+    let (_, body) = send(&app, "POST", "/api/chat/stream", Some(json!({"model": "test", "prompt": "Hi"}))).await;
+
+    let events = sse_events(&body);
+    let names: Vec<&str> = events.iter().map(|(e, _)| e.as_str()).collect();
+    assert_eq!(names, ["job", "token", "token", "error"]);
+    assert!(events[3].1["message"].as_str().unwrap().contains("malformed"));
+    assert_eq!(events[3].1["code"], "upstream_error");
+}
+
+#[tokio::test]
+async fn blank_chat_stream_is_an_error_and_not_saved() {
+    let (app, _, _dir) = test_app(MockProvider::scripted([""]));
+    let (_, body) = send(&app, "POST", "/api/chat/stream", Some(json!({"model": "test", "prompt": "Hi", "project": "novel"}))).await;
+
+    let events = sse_events(&body);
+    let names: Vec<&str> = events.iter().map(|(e, _)| e.as_str()).collect();
+    assert_eq!(names, ["job", "error"]);
+    assert_eq!(events[1].1["message"], "model returned empty response");
+
+    let (_, sessions) = get(&app, "/api/chat/sessions/novel").await;
+    assert_eq!(sessions, json!([]));
+}
+
+#[tokio::test]
+async fn cancel_stops_a_running_chat() {
+    let (app, _, _dir) = test_app(MockProvider::echo().with_latency(Duration::from_secs(30)));
+    let pending = {
+        let app = app.clone();
+        tokio::spawn(async move { post(&app, "/api/chat", json!({"model": "test", "prompt": "Hi", "request_id": "slow-1"})).await })
+    };
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    let (status, cancel) = post(&app, "/api/jobs/slow-1/cancel", json!({})).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(cancel["cancelled"], true);
+
+    let (status, body) = tokio::time::timeout(Duration::from_secs(5), pending).await.unwrap().unwrap();
+    assert_eq!(status.as_u16(), 499);
+    assert_eq!(body["code"], "cancelled");
+    assert_eq!(body["retryable"], false);
+
+    let (status, body) = post(&app, "/api/jobs/slow-1/cancel", json!({})).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert_eq!(body["code"], "not_found");
+    assert_eq!(body["retryable"], false);
+}
+
+#[tokio::test]
+async fn project_dictionary_silences_spellcheck() {
+    let (app, _, _dir) = test_app(MockProvider::echo());
+    let text = json!({"text": "Eldrath lit the glowstone.", "project": "novel"});
+    let flagged = |body: &Value| {
+        body["issues"].as_array().unwrap().iter().any(|i| i["kind"] == "spelling" && i["message"].as_str().unwrap().contains("'glowstone'"))
+    };
+
+    let (_, before) = post(&app, "/api/heuristics", text.clone()).await;
+    assert!(flagged(&before));
+
+    let (status, _) = post(&app, "/api/dictionary/novel", json!({"words": ["glowstone"]})).await;
+    assert_eq!(status, StatusCode::OK);
+    let (_, after) = post(&app, "/api/heuristics", text).await;
+    assert!(!flagged(&after));
+}
+
+#[tokio::test]
+async fn models_and_health_report_installed_tiers() {
+    let tiers = Tiers::default();
+    let (app, _, _dir) = test_app(MockProvider::echo().with_models([tiers.small.model.as_str(), "llama3.2"]));
+
+    let (status, body) = get(&app, "/api/models").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["backend"], "mock");
+    assert_eq!(body["models"].as_array().unwrap().len(), 2);
+
+    let (status, health) = get(&app, "/api/health").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(health["status"], "degraded");
+    assert_eq!(health["backend"]["reachable"], true);
+    assert_eq!(health["tiers"]["small"]["installed"], true);
+    assert_eq!(health["tiers"]["medium"]["installed"], true);
+    assert_eq!(health["tiers"]["large"]["installed"], false);
+}
+
+#[tokio::test]
+async fn health_reports_an_unreachable_backend() {
+    let (app, _, _dir) = test_app(MockProvider::echo().with_fault(MockFault::Error("connection refused".into())));
+
+    let (status, health) = get(&app, "/api/health").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(health["status"], "unavailable");
+    assert_eq!(health["backend"]["reachable"], false);
+    assert!(health["backend"]["error"]["message"].as_str().unwrap().contains("connection refused"));
+    assert!(health["tiers"]["small"]["installed"].is_null());
+
+    let (status, _) = get(&app, "/api/models").await;
+    assert_eq!(status, StatusCode::BAD_GATEWAY);
+}
+
+#[tokio::test]
+async fn warming_a_tier_loads_its_model() {
+    let small = Tiers::default().small.model;
+    let (app, _, _dir) = test_app(MockProvider::echo().with_models([small.as_str()]));
+
+    let (status, body) = post(&app, "/api/models/warm", json!({"tier": "small"})).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["model"], small.as_str());
+
+    let (_, health) = get(&app, "/api/health").await;
+    assert_eq!(health["tiers"]["small"]["loaded"], true);
+    assert_eq!(health["backend"]["models_loaded"], json!([small]));
+
+    let (status, _) = post(&app, "/api/models/warm", json!({"model": "missing:7b"})).await;
+    assert_eq!(status, StatusCode::BAD_GATEWAY);
+    let (status, _) = post(&app, "/api/models/warm", json!({})).await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+}